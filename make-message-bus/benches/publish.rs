@@ -0,0 +1,55 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use make_message_bus::make_message_bus;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    any(feature = "record", feature = "serde"),
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct Large(#[allow(dead_code)] Vec<u8>);
+
+make_message_bus!(
+    bus::Toplevel => {
+        t1::SubTopic1 => {
+            t2::SubTopic2 => {
+                t3::SubTopic3 => {
+                    t4::SubTopic4 => {
+                        Leaf [16] => crate::Large,
+                    },
+                },
+            },
+        },
+    },
+);
+
+fn publish(c: &mut Criterion) {
+    let payload = Large(vec![0; 4096]);
+    let mut group = c.benchmark_group("publish");
+
+    group.bench_function("no_parent_subscribers", |b| {
+        b.iter(|| bus::t1::t2::t3::t4::Leaf::publish(payload.clone()))
+    });
+
+    let _sub = bus::Toplevel::subscribe();
+
+    group.bench_function("toplevel_subscriber", |b| {
+        b.iter(|| bus::t1::t2::t3::t4::Leaf::publish(payload.clone()))
+    });
+
+    // Every ancestor is sent to, which is what each publish used to cost even without subscribers
+    let _subs = (
+        bus::SubTopic1::subscribe(),
+        bus::t1::SubTopic2::subscribe(),
+        bus::t1::t2::SubTopic3::subscribe(),
+        bus::t1::t2::t3::SubTopic4::subscribe(),
+    );
+
+    group.bench_function("all_parent_subscribers", |b| {
+        b.iter(|| bus::t1::t2::t3::t4::Leaf::publish(payload.clone()))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, publish);
+criterion_main!(benches);
@@ -2,10 +2,29 @@ use make_message_bus::make_message_bus;
 
 //
 // Topic defintion:
-// TopicName [optional buffer size] => payload,
+// TopicName [optional buffer size, optional modes] => payload,
+//
+// The payload is any `'static + Clone + Send` type, e.g. `(u8, u16)`, `[u8; 16]` or `&'static str`.
+//
+// Topic modes:
+// latest - retain the latest value, `Topic::get()` returns it and new subscribers receive it first
+// history = N - retain the last N values, `Topic::subscribe_with_history()` receives them first
+// queue - work-queue, each value is received by one of the workers from `Topic::take_worker()`
+// lossless - `Topic::publish(..).await` waits for room instead of overwriting unreceived values
+// envelope - values carry a sequence number, timestamp and source, see `Topic::publish_from(..)`
+// subscribers = N - allow at most N subscribers at a time, also supported on subtopics
+//
+// Request/response topic definition, served via `Topic::serve(|request| ...)`:
+// TopicName [optional buffer size] => request payload -> response payload,
 //
 // Subtopic definition:
-// module_name::SubtopicName => { ... },
+// #[optional attributes] module_name::SubtopicName [optional subscribers = N] => { ... },
+//
+// Derives on a subtopic are also derived on its nested subtopics, derives and attributes of all
+// subtopics are given in a header, e.g. `#![derive(PartialEq)]`, before the toplevel subtopic.
+//
+// The toplevel subtopic takes `dead_letters = N`, generating a `DeadLetters` topic with buffer
+// size N (optional) receiving a `DroppedMessages` event whenever a subscriber misses values.
 //
 
 make_message_bus!(
@@ -0,0 +1,119 @@
+use super::{Lagged, TryRecvError};
+use ::async_broadcast::{broadcast, InactiveReceiver, RecvError, TrySendError};
+use std::time::Duration;
+
+/// Broadcast sender based on `async_broadcast`, usable with any executor.
+pub(crate) struct Sender<T> {
+    sender: ::async_broadcast::Sender<T>,
+    // Keeps the channel open while there are no subscribers
+    _inactive: InactiveReceiver<T>,
+}
+
+impl<T> Sender<T>
+where
+    T: Clone,
+{
+    pub(crate) fn new(capacity: usize) -> Self {
+        let (mut sender, receiver) = broadcast(capacity);
+
+        // Overwrite the oldest message when full, lagging subscribers are told what they missed
+        sender.set_overflow(true);
+
+        Self {
+            sender,
+            _inactive: receiver.deactivate(),
+        }
+    }
+
+    pub(crate) fn send(&self, msg: T) -> usize {
+        match self.sender.try_broadcast(msg) {
+            Ok(_) => self.sender.receiver_count(),
+            Err(TrySendError::Inactive(_)) => 0,
+            // Overflow is enabled and the inactive receiver keeps the channel open
+            Err(TrySendError::Full(_) | TrySendError::Closed(_)) => unreachable!(),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.sender.len()
+    }
+
+    pub(crate) fn receiver_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+
+    pub(crate) fn subscribe(&self) -> Receiver<T> {
+        Receiver(self.sender.new_receiver())
+    }
+}
+
+/// Broadcast receiver based on `async_broadcast`, usable with any executor.
+pub(crate) struct Receiver<T>(::async_broadcast::Receiver<T>);
+
+impl<T> Receiver<T>
+where
+    T: Clone,
+{
+    pub(crate) fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self.0.try_recv() {
+            Ok(msg) => Ok(msg),
+            Err(::async_broadcast::TryRecvError::Empty) => Err(TryRecvError::Empty),
+            Err(::async_broadcast::TryRecvError::Overflowed(n)) => Err(TryRecvError::Lagged(n)),
+            Err(::async_broadcast::TryRecvError::Closed) => unreachable!(), // Impossible to drop the sender
+        }
+    }
+
+    pub(crate) async fn recv(&mut self) -> Result<T, Lagged> {
+        match self.0.recv_direct().await {
+            Ok(msg) => Ok(msg),
+            Err(RecvError::Overflowed(n)) => Err(Lagged(n)),
+            Err(RecvError::Closed) => unreachable!(), // Impossible to drop the sender
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(feature = "stream")]
+mod stream {
+    use super::{Lagged, Receiver};
+    use ::async_broadcast::RecvError;
+    use core::{
+        pin::Pin,
+        task::{ready, Context, Poll},
+    };
+
+    /// Broadcast receiver which can be polled.
+    pub(crate) struct RecvStream<T>(::async_broadcast::Receiver<T>);
+
+    impl<T> Receiver<T>
+    where
+        T: Clone + Send + 'static,
+    {
+        pub(crate) fn into_stream(self) -> RecvStream<T> {
+            RecvStream(self.0)
+        }
+    }
+
+    impl<T> RecvStream<T>
+    where
+        T: Clone + Send + 'static,
+    {
+        pub(crate) fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, Lagged>> {
+            match ready!(Pin::new(&mut self.0).poll_recv(cx)) {
+                Some(Ok(msg)) => Poll::Ready(Ok(msg)),
+                Some(Err(RecvError::Overflowed(n))) => Poll::Ready(Err(Lagged(n))),
+                Some(Err(RecvError::Closed)) | None => unreachable!(), // Impossible to drop the sender
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+pub(crate) use stream::RecvStream;
+
+pub(crate) async fn sleep(duration: Duration) {
+    async_io::Timer::after(duration).await;
+}
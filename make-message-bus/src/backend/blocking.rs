@@ -0,0 +1,418 @@
+use super::{Lagged, TryRecvError};
+use core::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, MutexGuard, OnceLock, PoisonError, TryLockError,
+    },
+    thread::{self, Thread},
+    time::{Duration, Instant},
+};
+
+struct Channel<T> {
+    /// Messages not yet received by all receivers, with the number of receivers still to do so
+    queue: VecDeque<(T, usize)>,
+    /// Position of the first message in the queue
+    head: u64,
+    capacity: usize,
+}
+
+impl<T> Channel<T> {
+    fn tail(&self) -> u64 {
+        self.head + self.queue.len() as u64
+    }
+
+    /// Drop the messages all receivers have received.
+    fn pop_received(&mut self) {
+        while let Some((_, 0)) = self.queue.front() {
+            self.queue.pop_front();
+            self.head += 1;
+        }
+    }
+}
+
+struct Shared<T> {
+    channel: Mutex<Channel<T>>,
+    /// Number of receivers, only changed with the channel locked but readable without locking
+    receivers: AtomicUsize,
+    /// Registered with the channel locked, so no message is missed between checking and waiting
+    wakers: Arc<Wakers>,
+}
+
+/// Receivers waiting for a message.
+struct Wakers {
+    wakers: Mutex<Vec<Waker>>,
+    /// Set when a message was sent from an interrupt, the timer thread wakes the receivers
+    deferred: AtomicBool,
+}
+
+impl Wakers {
+    fn lock(&self) -> MutexGuard<'_, Vec<Waker>> {
+        // The wakers are always consistent, so a poisoned lock is of no concern
+        self.wakers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wake(&self) {
+        // Drained so the wakers keep their allocation, sending never frees memory
+        for waker in self.lock().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+fn lock<T>(shared: &Shared<T>) -> MutexGuard<'_, Channel<T>> {
+    // The channel is always consistent, so a poisoned lock is of no concern
+    shared
+        .channel
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Broadcast sender only depending on `std`, usable with any executor.
+pub(crate) struct Sender<T>(Arc<Shared<T>>);
+
+impl<T> Sender<T> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let wakers = Arc::new(Wakers {
+            wakers: Mutex::new(Vec::new()),
+            deferred: AtomicBool::new(false),
+        });
+
+        // Registered up front, sending from an interrupt can't allocate
+        timers().lock_deferred().push(wakers.clone());
+
+        Self(Arc::new(Shared {
+            channel: Mutex::new(Channel {
+                queue: VecDeque::with_capacity(capacity),
+                head: 0,
+                capacity,
+            }),
+            receivers: AtomicUsize::new(0),
+            wakers,
+        }))
+    }
+
+    pub(crate) fn send(&self, msg: T) -> usize {
+        let channel = lock(&self.0);
+        let receivers = self.send_locked(channel, msg);
+
+        if receivers > 0 {
+            self.0.wakers.wake();
+        }
+
+        receivers
+    }
+
+    /// Fails if the channel is locked by someone else.
+    ///
+    /// Waking runs executor code which could take its own locks, so the receivers are woken by
+    /// the timer thread instead.
+    pub(crate) fn try_send(&self, msg: T) -> Result<usize, T> {
+        let receivers = match self.0.channel.try_lock() {
+            Ok(channel) => self.send_locked(channel, msg),
+            Err(TryLockError::Poisoned(err)) => self.send_locked(err.into_inner(), msg),
+            Err(TryLockError::WouldBlock) => return Err(msg),
+        };
+
+        if receivers > 0 {
+            self.0.wakers.deferred.store(true, Ordering::Release);
+            timers().wake_deferred();
+        }
+
+        Ok(receivers)
+    }
+
+    fn send_locked(&self, mut channel: MutexGuard<'_, Channel<T>>, msg: T) -> usize {
+        let receivers = self.0.receivers.load(Ordering::Relaxed);
+
+        if receivers == 0 {
+            return 0;
+        }
+
+        // Overwrite the oldest message when full, lagging receivers are told what they missed
+        if channel.queue.len() == channel.capacity {
+            channel.queue.pop_front();
+            channel.head += 1;
+        }
+
+        channel.queue.push_back((msg, receivers));
+
+        receivers
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        lock(&self.0).queue.len()
+    }
+
+    /// Fails if the channel is locked by someone else.
+    pub(crate) fn try_len(&self) -> Option<usize> {
+        match self.0.channel.try_lock() {
+            Ok(channel) => Some(channel.queue.len()),
+            Err(TryLockError::Poisoned(err)) => Some(err.into_inner().queue.len()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    pub(crate) fn receiver_count(&self) -> usize {
+        self.0.receivers.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn subscribe(&self) -> Receiver<T> {
+        let channel = lock(&self.0);
+        self.0.receivers.fetch_add(1, Ordering::Relaxed);
+
+        Receiver {
+            pos: channel.tail(),
+            shared: self.0.clone(),
+        }
+    }
+}
+
+/// Broadcast receiver only depending on `std`, usable with any executor.
+pub(crate) struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+    /// Position of the next message to receive
+    pos: u64,
+}
+
+impl<T> Receiver<T>
+where
+    T: Clone,
+{
+    pub(crate) fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut channel = lock(&self.shared);
+
+        if self.pos < channel.head {
+            let missed = channel.head - self.pos;
+            self.pos = channel.head;
+
+            return Err(TryRecvError::Lagged(missed));
+        }
+
+        let index = (self.pos - channel.head) as usize;
+        let Some((msg, remaining)) = channel.queue.get_mut(index) else {
+            return Err(TryRecvError::Empty);
+        };
+
+        *remaining -= 1;
+        let msg = msg.clone();
+        self.pos += 1;
+        channel.pop_received();
+
+        Ok(msg)
+    }
+
+    pub(crate) fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, Lagged>> {
+        match self.try_recv() {
+            Ok(msg) => Poll::Ready(Ok(msg)),
+            Err(TryRecvError::Lagged(n)) => Poll::Ready(Err(Lagged(n))),
+            Err(TryRecvError::Empty) => {
+                let channel = lock(&self.shared);
+
+                // A message could have been sent since trying
+                if self.pos < channel.tail() {
+                    cx.waker().wake_by_ref();
+                } else {
+                    let mut wakers = self.shared.wakers.lock();
+
+                    if !wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                        wakers.push(cx.waker().clone());
+                    }
+                }
+
+                Poll::Pending
+            }
+        }
+    }
+
+    pub(crate) async fn recv(&mut self) -> Result<T, Lagged> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.pos == lock(&self.shared).tail()
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut channel = lock(&self.shared);
+        let start = self.pos.saturating_sub(channel.head) as usize;
+
+        // Mark the messages this receiver never got to as received
+        for (_, remaining) in channel.queue.iter_mut().skip(start) {
+            *remaining -= 1;
+        }
+
+        self.shared.receivers.fetch_sub(1, Ordering::Relaxed);
+        channel.pop_received();
+    }
+}
+
+#[cfg(feature = "stream")]
+pub(crate) type RecvStream<T> = Receiver<T>;
+
+#[cfg(feature = "stream")]
+impl<T> Receiver<T> {
+    pub(crate) fn into_stream(self) -> RecvStream<T> {
+        self
+    }
+}
+
+pub(crate) async fn sleep(duration: Duration) {
+    Sleep {
+        deadline: Instant::now() + duration,
+        id: None,
+    }
+    .await
+}
+
+/// Timer woken by the timer thread once its deadline has passed.
+struct Sleep {
+    deadline: Instant,
+    /// Set while the timer is pending
+    id: Option<u64>,
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let timers = timers();
+        let mut pending = timers.lock();
+
+        if Instant::now() >= self.deadline {
+            if let Some(id) = self.id.take() {
+                pending.remove(&(self.deadline, id));
+            }
+
+            return Poll::Ready(());
+        }
+
+        let id = *self
+            .id
+            .get_or_insert_with(|| timers.next_id.fetch_add(1, Ordering::Relaxed));
+        let key = (self.deadline, id);
+
+        match pending.get_mut(&key) {
+            Some(waker) => waker.clone_from(cx.waker()),
+            None => {
+                pending.insert(key, cx.waker().clone());
+
+                // The timer thread sleeps until the earliest deadline, wake it if that changed
+                if pending.first_key_value().map(|(first, _)| *first) == Some(key) {
+                    timers.thread.unpark();
+                }
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            timers().lock().remove(&(self.deadline, id));
+        }
+    }
+}
+
+/// The pending timers, woken by a single thread shared by all timers, which also wakes the
+/// receivers of messages sent from interrupts.
+struct Timers {
+    /// Wakers of the pending timers by deadline and id
+    pending: Mutex<BTreeMap<(Instant, u64), Waker>>,
+    next_id: AtomicU64,
+    thread: Thread,
+    /// The receivers of every channel
+    deferred: Mutex<Vec<Arc<Wakers>>>,
+    /// Set when a channel has receivers to wake
+    wake_deferred: AtomicBool,
+}
+
+impl Timers {
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<(Instant, u64), Waker>> {
+        // The timers are always consistent, so a poisoned lock is of no concern
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_deferred(&self) -> MutexGuard<'_, Vec<Arc<Wakers>>> {
+        self.deferred.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Have the timer thread wake the receivers of the channels marked as deferred, without
+    /// locking or allocating.
+    fn wake_deferred(&self) {
+        self.wake_deferred.store(true, Ordering::Release);
+        self.thread.unpark();
+    }
+}
+
+fn timers() -> &'static Timers {
+    static TIMERS: OnceLock<Timers> = OnceLock::new();
+
+    TIMERS.get_or_init(|| {
+        let thread = thread::Builder::new()
+            .name("message-bus-timer".into())
+            .spawn(run_timers)
+            .expect("failed to spawn the timer thread");
+
+        Timers {
+            pending: Mutex::new(BTreeMap::new()),
+            next_id: AtomicU64::new(0),
+            thread: thread.thread().clone(),
+            deferred: Mutex::new(Vec::new()),
+            wake_deferred: AtomicBool::new(false),
+        }
+    })
+}
+
+/// Wake the timers as their deadlines pass and the receivers of messages sent from interrupts,
+/// parking the thread until the next deadline.
+fn run_timers() {
+    // Waits for the timers to be initialized by the thread which spawned this one
+    let timers = timers();
+    let mut expired = Vec::new();
+
+    loop {
+        let mut pending = timers.lock();
+        let now = Instant::now();
+
+        while let Some(entry) = pending.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+
+            expired.push(entry.remove());
+        }
+
+        let next = pending
+            .first_key_value()
+            .map(|((deadline, _), _)| *deadline);
+        drop(pending);
+
+        for waker in expired.drain(..) {
+            waker.wake();
+        }
+
+        if timers.wake_deferred.swap(false, Ordering::Acquire) {
+            for wakers in timers.lock_deferred().iter() {
+                if wakers.deferred.swap(false, Ordering::Acquire) {
+                    wakers.wake();
+                }
+            }
+        }
+
+        // A timer added since unlocking unparks the thread, so parking returns right away
+        match next {
+            Some(deadline) => thread::park_timeout(deadline.saturating_duration_since(now)),
+            None => thread::park(),
+        }
+    }
+}
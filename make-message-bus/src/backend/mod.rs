@@ -0,0 +1,122 @@
+//! Broadcast channel and timer implementations the topics are built on, selected with the
+//! `tokio`, `async-broadcast` and `blocking` features.
+//!
+//! Every backend provides the same interface:
+//!
+//! - `Sender<T>` with `new(capacity)`, `send(msg) -> usize`, `len()`, `receiver_count()` and
+//!   `subscribe() -> Receiver<T>`, messages sent without receivers are dropped. The `blocking`
+//!   backend also has `try_send(msg) -> Result<usize, T>` and `try_len() -> Option<usize>`, which
+//!   never allocate and fail instead of waiting for a lock held by someone else, for publishing
+//!   from interrupts. The `tokio` and `async-broadcast` channels always take their lock.
+//! - `Receiver<T>` with `try_recv()`, `recv().await`, `poll_recv(cx)` and `is_empty()`, where a
+//!   receiver which has lagged behind gets `Lagged(n)` once and then continues from the oldest
+//!   message still in the channel.
+//! - `sleep(duration).await`.
+//!
+//! If several backends are enabled `tokio` is used first, then `async-broadcast`.
+
+use core::{
+    future::{poll_fn, Future},
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+use std::{
+    sync::Arc,
+    task::Wake,
+    thread::{self, Thread},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "tokio")]
+mod tokio;
+#[cfg(feature = "tokio")]
+pub(crate) use self::tokio::*;
+
+#[cfg(all(not(feature = "tokio"), feature = "async-broadcast"))]
+mod async_broadcast;
+#[cfg(all(not(feature = "tokio"), feature = "async-broadcast"))]
+pub(crate) use self::async_broadcast::*;
+
+#[cfg(all(
+    not(feature = "tokio"),
+    not(feature = "async-broadcast"),
+    feature = "blocking"
+))]
+mod blocking;
+#[cfg(all(
+    not(feature = "tokio"),
+    not(feature = "async-broadcast"),
+    feature = "blocking"
+))]
+pub(crate) use self::blocking::*;
+
+#[cfg(not(any(feature = "tokio", feature = "async-broadcast", feature = "blocking")))]
+compile_error!(
+    "Select a backend with one of the `tokio`, `async-broadcast` or `blocking` features"
+);
+
+/// Error when receiving without waiting.
+#[derive(Debug)]
+pub(crate) enum TryRecvError {
+    /// There are no new messages.
+    Empty,
+    /// The receiver lagged behind and missed this many messages.
+    Lagged(u64),
+}
+
+pub(crate) use crate::Lagged;
+
+/// Run `future`, giving up after `duration`.
+pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Option<F::Output> {
+    let mut future = pin!(future);
+    let mut sleep = pin!(sleep(duration));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = future.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+
+        sleep.as_mut().poll(cx).map(|_| None)
+    })
+    .await
+}
+
+/// Wakes a thread parked in [`block_on`].
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run `future` on the current thread, parking it while waiting, giving up at `deadline`.
+pub(crate) fn block_on<F: Future>(future: F, deadline: Option<Instant>) -> Option<F::Output> {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return Some(output);
+        }
+
+        // Parking can wake up spuriously, the future is polled again either way
+        match deadline {
+            Some(deadline) => {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+
+                if remaining.is_zero() {
+                    return None;
+                }
+
+                thread::park_timeout(remaining);
+            }
+            None => thread::park(),
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use super::{Lagged, TryRecvError};
+use ::tokio::sync::broadcast::{self, error};
+use std::time::Duration;
+
+/// Broadcast sender based on `tokio::sync::broadcast`.
+pub(crate) struct Sender<T>(broadcast::Sender<T>);
+
+impl<T> Sender<T>
+where
+    T: Clone,
+{
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self(broadcast::channel(capacity).0)
+    }
+
+    pub(crate) fn send(&self, msg: T) -> usize {
+        self.0.send(msg).unwrap_or(0)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub(crate) fn receiver_count(&self) -> usize {
+        self.0.receiver_count()
+    }
+
+    pub(crate) fn subscribe(&self) -> Receiver<T> {
+        Receiver(self.0.subscribe())
+    }
+}
+
+/// Broadcast receiver based on `tokio::sync::broadcast`.
+pub(crate) struct Receiver<T>(broadcast::Receiver<T>);
+
+impl<T> Receiver<T>
+where
+    T: Clone,
+{
+    pub(crate) fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self.0.try_recv() {
+            Ok(msg) => Ok(msg),
+            Err(error::TryRecvError::Empty) => Err(TryRecvError::Empty),
+            Err(error::TryRecvError::Lagged(n)) => Err(TryRecvError::Lagged(n)),
+            Err(error::TryRecvError::Closed) => unreachable!(), // Impossible to drop the sender
+        }
+    }
+
+    pub(crate) async fn recv(&mut self) -> Result<T, Lagged> {
+        match self.0.recv().await {
+            Ok(msg) => Ok(msg),
+            Err(error::RecvError::Lagged(n)) => Err(Lagged(n)),
+            Err(error::RecvError::Closed) => unreachable!(), // Impossible to drop the sender
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+#[cfg(feature = "stream")]
+mod stream {
+    use super::{Lagged, Receiver};
+    use core::{
+        pin::Pin,
+        task::{ready, Context, Poll},
+    };
+    use futures_core::Stream;
+    use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+
+    /// Broadcast receiver which can be polled, based on `tokio_stream::wrappers::BroadcastStream`.
+    pub(crate) struct RecvStream<T>(BroadcastStream<T>);
+
+    impl<T> Receiver<T>
+    where
+        T: Clone + Send + 'static,
+    {
+        pub(crate) fn into_stream(self) -> RecvStream<T> {
+            RecvStream(BroadcastStream::new(self.0))
+        }
+    }
+
+    impl<T> RecvStream<T>
+    where
+        T: Clone + Send + 'static,
+    {
+        pub(crate) fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, Lagged>> {
+            match ready!(Pin::new(&mut self.0).poll_next(cx)) {
+                Some(Ok(msg)) => Poll::Ready(Ok(msg)),
+                Some(Err(BroadcastStreamRecvError::Lagged(n))) => Poll::Ready(Err(Lagged(n))),
+                None => unreachable!(), // Impossible to drop the sender
+            }
+        }
+    }
+}
+
+#[cfg(feature = "stream")]
+pub(crate) use stream::RecvStream;
+
+pub(crate) async fn sleep(duration: Duration) {
+    ::tokio::time::sleep(duration).await
+}
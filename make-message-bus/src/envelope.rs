@@ -0,0 +1,161 @@
+use crate::{LagPolicy, Lagged, Subscriber, Timeout, Topic};
+use std::time::{Duration, Instant};
+
+/// A message with its publishing metadata, used on topics in envelope mode.
+#[derive(Clone, Debug)]
+pub struct Envelope<T> {
+    /// Sequence number of the message, starts at 0 and increases by 1 for every message published
+    /// on the topic.
+    pub seq: u64,
+    /// When the message was published.
+    pub published_at: Instant,
+    /// Identifier of the publisher, if it was given.
+    pub source: Option<&'static str>,
+    /// The message.
+    pub payload: T,
+    /// Span the message was published in, to link the handling of the message back to it.
+    ///
+    /// Only topics in envelope mode carry the span, subscribers of other topics get the bare
+    /// payload.
+    #[cfg(feature = "tracing")]
+    pub span: tracing::Span,
+}
+
+impl<T> Envelope<T> {
+    /// Create an envelope published now, the sequence number is set when it's published on a
+    /// topic.
+    pub fn new(payload: T, source: Option<&'static str>) -> Self {
+        Self {
+            seq: 0,
+            published_at: Instant::now(),
+            source,
+            payload,
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+        }
+    }
+
+    /// Create an envelope published now without capturing the current span, for interrupt and
+    /// signal handlers where reading the span could block on the tracing subscriber.
+    pub fn new_untraced(payload: T, source: Option<&'static str>) -> Self {
+        Self {
+            seq: 0,
+            published_at: Instant::now(),
+            source,
+            payload,
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::none(),
+        }
+    }
+
+    fn set_seq(&mut self, seq: u64) {
+        self.seq = seq;
+    }
+}
+
+impl<T, const CAP: usize, const SUBS: usize> Topic<Envelope<T>, CAP, SUBS>
+where
+    T: Clone,
+{
+    /// Assign sequence numbers to the envelopes published on the topic.
+    pub const fn sequenced(mut self) -> Self {
+        self.sequence = Some(Envelope::set_seq);
+        self
+    }
+}
+
+/// A subscriber to a topic in envelope mode on the bus.
+pub struct EnvelopeSubscriber<T: 'static>(Subscriber<Envelope<T>>);
+
+impl<T> EnvelopeSubscriber<T> {
+    /// Wrap a subscriber to a topic of envelopes.
+    pub fn new(subscriber: Subscriber<Envelope<T>>) -> Self {
+        Self(subscriber)
+    }
+}
+
+impl<T> EnvelopeSubscriber<T>
+where
+    T: Clone,
+{
+    /// Set what the subscriber does when it lags behind, [`LagPolicy::Skip`] by default.
+    pub fn with_lag_policy(self, lag_policy: LagPolicy) -> Self {
+        Self(self.0.with_lag_policy(lag_policy))
+    }
+
+    /// Name the subscriber, reported on the dead-letter topic when it misses messages.
+    pub fn with_name(self, name: &'static str) -> Self {
+        Self(self.0.with_name(name))
+    }
+
+    /// Tries to receive a value, will return `None` if there are none.
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.try_recv_envelope().map(|envelope| envelope.payload)
+    }
+
+    /// Receive a value from the bus.
+    pub async fn recv(&mut self) -> T {
+        self.recv_envelope().await.payload
+    }
+
+    /// Receive a value from the bus, see [`Subscriber::recv_checked`].
+    pub async fn recv_checked(&mut self) -> Result<T, Lagged> {
+        Ok(self.0.recv_checked().await?.payload)
+    }
+
+    /// Receive a value from the bus, giving up after `timeout`.
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Result<T, Timeout> {
+        Ok(self.0.recv_timeout(timeout).await?.payload)
+    }
+
+    /// Receive a value from the bus, giving up at `deadline`.
+    pub async fn recv_deadline(&mut self, deadline: Instant) -> Result<T, Timeout> {
+        Ok(self.0.recv_deadline(deadline).await?.payload)
+    }
+
+    /// Receive up to `max` values into `buffer`, see [`Subscriber::recv_many`].
+    pub async fn recv_many(&mut self, buffer: &mut Vec<T>, max: usize) -> usize {
+        self.0
+            .recv_many_map(buffer, max, |envelope| envelope.payload)
+            .await
+    }
+
+    /// Receive a value from the bus, parking the thread until there is one, see
+    /// [`Subscriber::blocking_recv`].
+    pub fn blocking_recv(&mut self) -> T {
+        self.0.blocking_recv().payload
+    }
+
+    /// Receive a value from the bus, parking the thread until there is one or `timeout` has
+    /// passed, see [`Subscriber::blocking_recv_timeout`].
+    pub fn blocking_recv_timeout(&mut self, timeout: Duration) -> Result<T, Timeout> {
+        Ok(self.0.blocking_recv_timeout(timeout)?.payload)
+    }
+
+    /// Tries to receive a value with its metadata, will return `None` if there are none.
+    pub fn try_recv_envelope(&mut self) -> Option<Envelope<T>> {
+        self.0.try_recv()
+    }
+
+    /// Receive a value with its metadata from the bus.
+    ///
+    /// Messages missed due to lagging can be found from gaps in [`Envelope::seq`].
+    pub async fn recv_envelope(&mut self) -> Envelope<T> {
+        self.0.recv().await
+    }
+
+    /// Checks if there is a message on the topic.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Number of messages missed since last time this was called.
+    pub fn messages_lost(&mut self) -> u64 {
+        self.0.messages_lost()
+    }
+
+    /// Get the underlying subscriber, which receives the values with their metadata.
+    pub fn into_inner(self) -> Subscriber<Envelope<T>> {
+        self.0
+    }
+}
@@ -0,0 +1,358 @@
+use crate::{DroppedMessages, LagPolicy, LagReporter, Lagged, TopicStats, WouldBlock};
+use core::{
+    cell::RefCell,
+    future::poll_fn,
+    task::{Context, Poll, Waker},
+};
+use critical_section::Mutex;
+
+/// Topic type used in static storage in codegen, `no_std` and heap-free.
+///
+/// The last `CAP` messages are kept in an inline ring buffer and each of the `SUBS` subscriber
+/// slots stores its position and waker inline, all access goes through a critical section.
+pub struct Topic<T: Clone, const CAP: usize, const SUBS: usize> {
+    state: Mutex<RefCell<State<T, CAP, SUBS>>>,
+    path: Option<&'static str>,
+    dead_letters: Option<fn(DroppedMessages)>,
+}
+
+struct State<T, const CAP: usize, const SUBS: usize> {
+    buffer: [Option<T>; CAP],
+    /// Position of the next message to publish, the buffer holds the `CAP` messages before it
+    tail: u64,
+    slots: [Slot; SUBS],
+    published: u64,
+    /// Messages missed by subscribers
+    lost: u64,
+}
+
+struct Slot {
+    subscribed: bool,
+    /// Position of the next message to receive
+    pos: u64,
+    waker: Option<Waker>,
+}
+
+impl Slot {
+    const FREE: Slot = Slot {
+        subscribed: false,
+        pos: 0,
+        waker: None,
+    };
+}
+
+impl<T, const CAP: usize, const SUBS: usize> Topic<T, CAP, SUBS>
+where
+    T: Clone + Send + 'static,
+{
+    /// Create a new topic.
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(State {
+                buffer: [const { None }; CAP],
+                tail: 0,
+                slots: [Slot::FREE; SUBS],
+                published: 0,
+                lost: 0,
+            })),
+            path: None,
+            dead_letters: None,
+        }
+    }
+
+    /// Set the path of the topic, e.g. `Toplevel/SubTopic/Topic`, used when reporting messages
+    /// missed by the subscribers.
+    pub const fn path(mut self, path: &'static str) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Report messages missed by the subscribers with `report`.
+    pub const fn dead_letters(mut self, report: fn(DroppedMessages)) -> Self {
+        self.dead_letters = Some(report);
+        self
+    }
+
+    fn reporter(&self) -> LagReporter {
+        LagReporter {
+            topic: self.path,
+            subscriber: None,
+            dead_letters: self.dead_letters,
+        }
+    }
+
+    /// Subscribe to the topic.
+    ///
+    /// # Panics
+    ///
+    /// If all `SUBS` subscriber slots are taken.
+    pub fn subscribe(&'static self) -> Subscriber<T> {
+        let slot = critical_section::with(|cs| {
+            let mut state = self.state.borrow_ref_mut(cs);
+            let tail = state.tail;
+
+            let (index, slot) = state
+                .slots
+                .iter_mut()
+                .enumerate()
+                .find(|(_, slot)| !slot.subscribed)
+                .expect("the topic already has the maximum number of subscribers");
+
+            slot.subscribed = true;
+            slot.pos = tail;
+
+            index
+        });
+
+        Subscriber {
+            topic: self,
+            slot,
+            missed_messages: 0,
+            lag_policy: LagPolicy::Skip,
+            reporter: self.reporter(),
+        }
+    }
+
+    /// Number of subscribers of the topic.
+    pub fn receiver_count(&self) -> usize {
+        critical_section::with(|cs| {
+            let state = self.state.borrow_ref(cs);
+
+            state.slots.iter().filter(|slot| slot.subscribed).count()
+        })
+    }
+
+    /// Checks if the topic has any subscribers.
+    pub fn has_subscribers(&self) -> bool {
+        self.receiver_count() > 0
+    }
+
+    /// Snapshot of the topic's statistics.
+    pub fn stats(&self) -> TopicStats {
+        critical_section::with(|cs| {
+            let state = self.state.borrow_ref(cs);
+
+            TopicStats {
+                published: state.published,
+                subscribers: state.slots.iter().filter(|slot| slot.subscribed).count(),
+                messages_lost: state.lost,
+            }
+        })
+    }
+
+    /// Count a message published to a sub-topic without subscribers, which is skipped instead of
+    /// wrapping the payload for nobody.
+    pub fn count_publish(&self) {
+        critical_section::with(|cs| self.state.borrow_ref_mut(cs).published += 1);
+    }
+
+    /// Publish to a topic, returns the number of subscribers which received the message.
+    ///
+    /// If a subscriber has not received the oldest of the `CAP` messages it is overwritten.
+    ///
+    /// Only takes a critical section, so it can be called from interrupt handlers.
+    pub fn publish(&self, payload: T) -> usize {
+        critical_section::with(|cs| {
+            let mut state = self.state.borrow_ref_mut(cs);
+            let mut receivers = 0;
+            state.published += 1;
+
+            for slot in state.slots.iter_mut().filter(|slot| slot.subscribed) {
+                receivers += 1;
+
+                if let Some(waker) = slot.waker.take() {
+                    waker.wake();
+                }
+            }
+
+            if receivers > 0 {
+                let index = (state.tail % CAP as u64) as usize;
+                state.buffer[index] = Some(payload);
+                state.tail += 1;
+            }
+
+            receivers
+        })
+    }
+
+    /// Publish without blocking or allocating, see [`Topic::publish`] which never blocks.
+    pub fn try_publish_nonblocking(&self, payload: T) -> Result<usize, WouldBlock<T>> {
+        Ok(self.publish(payload))
+    }
+
+    /// Publish from an interrupt handler, see [`Topic::publish`] which never blocks.
+    pub fn publish_from_isr(&self, payload: T) -> usize {
+        self.publish(payload)
+    }
+
+    /// Number of messages dropped by [`Topic::publish_from_isr`], always 0 as the critical
+    /// section can always be taken.
+    pub fn dropped_publishes(&self) -> u64 {
+        0
+    }
+}
+
+impl<T, const CAP: usize, const SUBS: usize> Default for Topic<T, CAP, SUBS>
+where
+    T: Clone + Send + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Receiving side of a topic, hides the size of the topic from the subscriber.
+trait Channel<T>: Sync {
+    /// Receive from the slot, registers the waker if there is nothing to receive.
+    fn poll_recv(&self, slot: usize, cx: Option<&mut Context<'_>>) -> Poll<Result<T, u64>>;
+
+    fn is_empty(&self, slot: usize) -> bool;
+
+    fn unsubscribe(&self, slot: usize);
+}
+
+impl<T, const CAP: usize, const SUBS: usize> Channel<T> for Topic<T, CAP, SUBS>
+where
+    T: Clone + Send,
+{
+    fn poll_recv(&self, slot: usize, cx: Option<&mut Context<'_>>) -> Poll<Result<T, u64>> {
+        critical_section::with(|cs| {
+            let mut state = self.state.borrow_ref_mut(cs);
+            let tail = state.tail;
+            let oldest = tail.saturating_sub(CAP as u64);
+            let slot = &mut state.slots[slot];
+
+            if slot.pos < oldest {
+                let missed = oldest - slot.pos;
+                slot.pos = oldest;
+                state.lost += missed;
+
+                return Poll::Ready(Err(missed));
+            }
+
+            if slot.pos == tail {
+                if let Some(cx) = cx {
+                    // Executors such as embassy give the same waker every poll, don't replace it
+                    match &slot.waker {
+                        Some(waker) if waker.will_wake(cx.waker()) => {}
+                        _ => slot.waker = Some(cx.waker().clone()),
+                    }
+                }
+
+                return Poll::Pending;
+            }
+
+            let index = (slot.pos % CAP as u64) as usize;
+            slot.pos += 1;
+
+            match &state.buffer[index] {
+                Some(msg) => Poll::Ready(Ok(msg.clone())),
+                None => unreachable!(), // Everything before the tail has been published
+            }
+        })
+    }
+
+    fn is_empty(&self, slot: usize) -> bool {
+        critical_section::with(|cs| {
+            let state = self.state.borrow_ref(cs);
+
+            state.slots[slot].pos == state.tail
+        })
+    }
+
+    fn unsubscribe(&self, slot: usize) {
+        critical_section::with(|cs| {
+            self.state.borrow_ref_mut(cs).slots[slot] = Slot::FREE;
+        })
+    }
+}
+
+/// A subscriber to a topic on the bus.
+pub struct Subscriber<T: 'static> {
+    topic: &'static dyn Channel<T>,
+    slot: usize,
+    missed_messages: u64,
+    lag_policy: LagPolicy,
+    reporter: LagReporter,
+}
+
+impl<T> Subscriber<T>
+where
+    T: Clone,
+{
+    /// Set what the subscriber does when it lags behind, [`LagPolicy::Skip`] by default.
+    pub fn with_lag_policy(mut self, lag_policy: LagPolicy) -> Self {
+        self.lag_policy = lag_policy;
+        self
+    }
+
+    /// Name the subscriber, reported on the dead-letter topic when it misses messages.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.reporter.subscriber = Some(name);
+        self
+    }
+
+    /// Tries to receive a value, will return `None` if there are none.
+    pub fn try_recv(&mut self) -> Option<T> {
+        loop {
+            match self.topic.poll_recv(self.slot, None) {
+                Poll::Ready(Ok(msg)) => return Some(msg),
+                Poll::Ready(Err(missed)) => {
+                    // Only `recv_checked` reports the lag as an error
+                    let _ = self.lagged(missed);
+                }
+                Poll::Pending => return None,
+            }
+        }
+    }
+
+    /// Receive a value from the bus.
+    ///
+    /// Waits by registering the task's waker, so any executor works, e.g. embassy tasks and RTIC
+    /// async software tasks.
+    pub async fn recv(&mut self) -> T {
+        loop {
+            if let Ok(msg) = self.recv_checked().await {
+                return msg;
+            }
+        }
+    }
+
+    /// Receive a value from the bus, returns `Err(Lagged(n))` if `n` messages were missed and
+    /// the subscriber has [`LagPolicy::Error`].
+    ///
+    /// The next call continues from the oldest message still on the topic.
+    pub async fn recv_checked(&mut self) -> Result<T, Lagged> {
+        loop {
+            match poll_fn(|cx| self.topic.poll_recv(self.slot, Some(cx))).await {
+                Ok(msg) => return Ok(msg),
+                Err(missed) => self.lagged(missed)?,
+            }
+        }
+    }
+
+    /// Checks if there is a message on the topic.
+    pub fn is_empty(&self) -> bool {
+        self.topic.is_empty(self.slot)
+    }
+
+    fn lagged(&mut self, n: u64) -> Result<(), Lagged> {
+        self.reporter.report(n);
+
+        self.lag_policy.lagged(n, &mut self.missed_messages)
+    }
+
+    /// Number of messages missed since last time this was called.
+    pub fn messages_lost(&mut self) -> u64 {
+        let n = self.missed_messages;
+        self.missed_messages = 0;
+
+        n
+    }
+}
+
+impl<T> Drop for Subscriber<T> {
+    fn drop(&mut self) {
+        self.topic.unsubscribe(self.slot);
+    }
+}
@@ -1,84 +1,285 @@
 //! Crate
 
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs)]
 
+use core::fmt;
 pub use message_bus_macros::make_message_bus;
-use once_cell::sync::Lazy;
-use tokio::sync::broadcast::{
-    channel,
-    error::{RecvError, TryRecvError},
-    Receiver, Sender,
-};
-
-/// Topic type used in static storage in codegen.
-pub struct Topic<T: Clone>(Lazy<Sender<T>>);
-
-impl<T> Topic<T>
-where
-    T: Clone,
-{
-    /// Create a new topic.
-    pub const fn new<const CAP: usize>() -> Self {
-        Self(Lazy::new(|| channel(CAP).0))
-    }
-
-    /// Subscribe to the topic.
-    pub fn subscribe(&self) -> Subscriber<T> {
-        Subscriber {
-            recv: self.0.subscribe(),
-            missed_messages: 0,
+
+#[cfg(feature = "std")]
+mod backend;
+#[cfg(feature = "std")]
+mod envelope;
+#[cfg(not(feature = "std"))]
+mod inline;
+#[cfg(feature = "mcap")]
+mod mcap;
+#[cfg(feature = "prometheus")]
+mod prometheus;
+#[cfg(feature = "std")]
+mod queue;
+#[cfg(feature = "record")]
+mod record;
+#[cfg(feature = "std")]
+mod rpc;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(all(feature = "std", feature = "stream"))]
+mod stream;
+#[cfg(feature = "std")]
+mod topic;
+
+#[cfg(feature = "std")]
+pub use envelope::{Envelope, EnvelopeSubscriber};
+#[cfg(not(feature = "std"))]
+pub use inline::{Subscriber, Topic};
+#[cfg(feature = "mcap")]
+pub use mcap::{McapReader, McapWriter};
+#[cfg(feature = "std")]
+pub use queue::{Queue, Worker};
+#[cfg(feature = "record")]
+pub use record::{LogRecord, Recordable, Recorder, ReplayError, ReplaySpeed, Replayer};
+#[cfg(feature = "std")]
+pub use rpc::{Reply, Request, Responder, Rpc, RpcError};
+#[cfg(all(feature = "std", feature = "stream"))]
+pub use stream::SubscriberStream;
+#[cfg(feature = "std")]
+pub use topic::{Subscriber, Timeout, Topic};
+
+/// Used by the generated code, not public API.
+#[doc(hidden)]
+pub mod __private {
+    use core::marker::PhantomData;
+
+    /// Resolves to the payload type only if it can be used as a payload, so an invalid payload is
+    /// reported once at its type instead of everywhere the generated code uses it.
+    pub struct Checked<T: ?Sized>(PhantomData<T>);
+
+    /// Payloads of topics, cloned to every subscriber.
+    pub trait TopicPayload {
+        type Type;
+
+        fn clone(payload: &Self::Type) -> Self::Type;
+    }
+
+    impl<T: Clone + Send + 'static> TopicPayload for Checked<T> {
+        type Type = T;
+
+        fn clone(payload: &T) -> T {
+            payload.clone()
         }
     }
 
-    /// Publish to a topic.
-    pub fn publish(&self, payload: T) {
-        self.0.send(payload).ok();
+    /// Payloads of request/response topics, moved to the responder and back.
+    pub trait RpcPayload {
+        type Type;
+    }
+
+    impl<T: Send + 'static> RpcPayload for Checked<T> {
+        type Type = T;
+    }
+
+    /// Resolves `(Checks, T)` to `T`, the payload type of a sub-topic resolved after the checks of
+    /// the payloads it contains, so they're not reported again at the sub-topic.
+    pub trait AfterChecks {
+        type Type;
+    }
+
+    impl<Checks, T> AfterChecks for (Checks, T) {
+        type Type = T;
+    }
+
+    #[cfg(feature = "serde")]
+    pub use serde;
+    #[cfg(feature = "record")]
+    pub use serde_json;
+    #[cfg(feature = "tracing")]
+    pub use tracing;
+}
+
+#[cfg(not(any(feature = "std", feature = "critical-section")))]
+compile_error!(
+    "Select a backend with one of the `tokio`, `async-broadcast`, `blocking` or `critical-section` features"
+);
+
+/// Maximum number of subscribers of topics which don't set `[subscribers = N]`.
+///
+/// With `std` the number of subscribers is unbounded, without it every subscriber needs a slot
+/// in the topic's static storage.
+pub const DEFAULT_MAX_SUBSCRIBERS: usize = if cfg!(feature = "std") { usize::MAX } else { 4 };
+
+/// Report of how many subscribers received a published message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PublishReport {
+    /// Number of subscribers of the topic which received the message.
+    pub receivers: usize,
+    /// Number of subscribers of the parent sub-topics which received the message.
+    pub parent_receivers: usize,
+}
+
+impl PublishReport {
+    /// Checks if any subscriber, direct or through a parent sub-topic, received the message.
+    pub fn is_received(&self) -> bool {
+        self.receivers + self.parent_receivers > 0
     }
 }
 
-/// A subscriber to a topic on the bus.
-pub struct Subscriber<T: Clone> {
-    recv: Receiver<T>,
-    missed_messages: u64,
+/// What a subscriber does when it lagged behind and messages it had not received were overwritten.
+///
+/// The missed messages are always counted in the subscriber's `messages_lost()`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LagPolicy {
+    /// Skip to the oldest message still on the topic.
+    #[default]
+    Skip,
+    /// Skip to the oldest message still on the topic, and return `Err(Lagged(n))` from
+    /// `recv_checked()` where the messages were missed.
+    Error,
+    /// Panic when messages are missed, meant for tests.
+    Panic,
 }
 
-impl<T> Subscriber<T>
-where
-    T: Clone,
-{
-    /// Tries to receive a value, will return `None` if there are none.
-    pub fn try_recv(&mut self) -> Option<T> {
-        loop {
-            match self.recv.try_recv() {
-                Ok(v) => return Some(v),
-                Err(TryRecvError::Empty) => return None,
-                Err(TryRecvError::Lagged(n)) => self.missed_messages += n,
-                Err(TryRecvError::Closed) => unreachable!(), // Impossible to drop the sender
-            }
+impl LagPolicy {
+    /// Apply the policy to `n` missed messages, after counting them in `missed_messages`.
+    pub(crate) fn lagged(self, n: u64, missed_messages: &mut u64) -> Result<(), Lagged> {
+        *missed_messages += n;
+
+        match self {
+            LagPolicy::Skip => Ok(()),
+            LagPolicy::Error => Err(Lagged(n)),
+            LagPolicy::Panic => panic!("the subscriber lagged behind and missed {n} messages"),
         }
     }
+}
 
-    /// Receive a value from the bus.
-    pub async fn recv(&mut self) -> T {
-        loop {
-            match self.recv.recv().await {
-                Ok(msg) => return msg,
-                Err(RecvError::Lagged(n)) => self.missed_messages += n,
-                Err(RecvError::Closed) => unreachable!(), // Impossible to drop the sender
-            }
+/// Error when a subscriber lagged behind and missed this many messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Lagged(pub u64);
+
+impl fmt::Display for Lagged {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the subscriber lagged behind and missed {} messages",
+            self.0
+        )
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Lagged {}
+
+/// Snapshot of the statistics of a topic.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TopicStats {
+    /// Number of messages published on the topic.
+    pub published: u64,
+    /// Current number of subscribers, or workers of work-queues.
+    pub subscribers: usize,
+    /// Total number of messages missed by the topic's subscribers, or dropped from work-queues.
+    pub messages_lost: u64,
+    /// When the last message was published, if any, recorded with `std`.
+    #[cfg(feature = "std")]
+    pub last_published: Option<std::time::SystemTime>,
+}
+
+/// Snapshot of the statistics of all topics on a bus, keyed by the full path of the topics,
+/// e.g. `Toplevel/SubTopic/Topic`. Created by the generated `stats()`.
+#[derive(Clone, Debug)]
+pub struct BusStats<const N: usize> {
+    /// The path and statistics of every topic and sub-topic, in definition order.
+    pub topics: [(&'static str, TopicStats); N],
+}
+
+impl<const N: usize> BusStats<N> {
+    /// Statistics of the topic at `path`.
+    pub fn get(&self, path: &str) -> Option<&TopicStats> {
+        self.topics
+            .iter()
+            .find(|(topic, _)| *topic == path)
+            .map(|(_, stats)| stats)
+    }
+
+    /// Iterate over the path and statistics of every topic.
+    pub fn iter(&self) -> impl Iterator<Item = &(&'static str, TopicStats)> {
+        self.topics.iter()
+    }
+}
+
+/// Event published on the dead-letter topic when a subscriber detects that it lagged behind and
+/// missed messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DroppedMessages {
+    /// Path of the topic, e.g. `Toplevel/SubTopic/Topic`.
+    pub topic: &'static str,
+    /// Number of messages the subscriber missed.
+    pub count: u64,
+    /// Name of the subscriber, if it was given.
+    pub subscriber: Option<&'static str>,
+}
+
+/// Where a subscriber reports the messages it missed, besides counting them.
+#[derive(Clone, Copy)]
+pub(crate) struct LagReporter {
+    /// Path of the topic
+    pub(crate) topic: Option<&'static str>,
+    /// Name of the subscriber
+    pub(crate) subscriber: Option<&'static str>,
+    pub(crate) dead_letters: Option<fn(DroppedMessages)>,
+}
+
+impl LagReporter {
+    pub(crate) fn report(&self, count: u64) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            topic = self.topic,
+            subscriber = self.subscriber,
+            missed = count,
+            "subscriber lagged behind"
+        );
+
+        if let Some(report) = self.dead_letters {
+            report(DroppedMessages {
+                topic: self.topic.unwrap_or_default(),
+                count,
+                subscriber: self.subscriber,
+            });
         }
     }
+}
+
+/// Error when publishing to a lossless topic where a subscriber has no room for the message.
+pub struct Full<T>(pub T);
 
-    /// Checks if there is a message on the topic.
-    pub fn is_empty(&self) -> bool {
-        self.recv.is_empty()
+impl<T> fmt::Debug for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Full(..)")
     }
+}
 
-    /// Number of messages missed since last time this was called.
-    pub fn messages_lost(&mut self) -> u64 {
-        let n = self.missed_messages;
-        self.missed_messages = 0;
+impl<T> fmt::Display for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a subscriber has no room for the message")
+    }
+}
 
-        n
+#[cfg(feature = "std")]
+impl<T> std::error::Error for Full<T> {}
+
+/// Error when publishing without blocking, and the message can't be published immediately.
+pub struct WouldBlock<T>(pub T);
+
+impl<T> fmt::Debug for WouldBlock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WouldBlock(..)")
+    }
+}
+
+impl<T> fmt::Display for WouldBlock<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("publishing would block")
     }
 }
+
+#[cfg(feature = "std")]
+impl<T> std::error::Error for WouldBlock<T> {}
@@ -0,0 +1,306 @@
+use crate::{
+    record::{read_record, replay_records, LogRecord, Recordable, ReplayError, ReplaySpeed},
+    Subscriber,
+};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufReader, BufWriter, Cursor, Read, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Magic bytes at the start and end of every MCAP file.
+const MAGIC: &[u8; 8] = b"\x89MCAP0\r\n";
+
+// Record opcodes
+const HEADER: u8 = 0x01;
+const FOOTER: u8 = 0x02;
+const CHANNEL: u8 = 0x04;
+const MESSAGE: u8 = 0x05;
+const CHUNK: u8 = 0x06;
+const DATA_END: u8 = 0x0f;
+
+/// Writes the messages received on a sub-topic to an MCAP file.
+///
+/// Every topic becomes a channel named by its full path, e.g. `/Toplevel/SubTopic/Topic`, with
+/// the payloads encoded as JSON. The channels are schemaless and the file is unchunked and without
+/// a summary section.
+pub struct McapWriter<W: Write> {
+    writer: W,
+    /// Full path and next sequence number of each channel, indexed by channel id - 1
+    channels: Vec<(&'static str, u32)>,
+    finished: bool,
+}
+
+impl McapWriter<BufWriter<File>> {
+    /// Create the MCAP file at `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> McapWriter<W> {
+    /// Start an MCAP file in `writer`.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+
+        let mut header = Vec::new();
+        put_str(&mut header, ""); // Profile
+        put_str(&mut header, "make-message-bus"); // Library
+        write_record(&mut writer, HEADER, &header)?;
+
+        Ok(Self {
+            writer,
+            channels: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// Write a message, timestamped now.
+    ///
+    /// The channel of the message's topic is written before its first message.
+    pub fn write<T: Recordable>(&mut self, message: &T) -> io::Result<()> {
+        let payload = message.encode_payload()?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+
+        let channel_id = self.channel(message)?;
+        let sequence = &mut self.channels[channel_id as usize - 1].1;
+
+        let mut record = Vec::with_capacity(22 + payload.len());
+        record.extend_from_slice(&channel_id.to_le_bytes());
+        record.extend_from_slice(&sequence.to_le_bytes());
+        record.extend_from_slice(&timestamp.to_le_bytes()); // Log time
+        record.extend_from_slice(&timestamp.to_le_bytes()); // Publish time
+        record.extend_from_slice(&payload);
+        *sequence = sequence.wrapping_add(1);
+
+        write_record(&mut self.writer, MESSAGE, &record)
+    }
+
+    /// Write every message received by `subscriber`, only returns if writing fails.
+    ///
+    /// The file is finished when the writer is dropped.
+    pub async fn record<T: Recordable + Clone>(
+        &mut self,
+        mut subscriber: Subscriber<T>,
+    ) -> io::Result<()> {
+        loop {
+            let message = subscriber.recv().await;
+
+            self.write(&message)?;
+        }
+    }
+
+    /// End the data section and write the footer, done on drop if not called.
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+
+        self.finished = true;
+
+        write_record(&mut self.writer, DATA_END, &0u32.to_le_bytes())?;
+        // No summary section
+        write_record(&mut self.writer, FOOTER, &[0; 20])?;
+        self.writer.write_all(MAGIC)?;
+        self.writer.flush()
+    }
+
+    /// The channel id of the message's topic, writing its channel if it's new.
+    fn channel<T: Recordable>(&mut self, message: &T) -> io::Result<u16> {
+        let path = message.path();
+
+        if let Some(index) = self.channels.iter().position(|(p, _)| *p == path) {
+            return Ok(index as u16 + 1);
+        }
+
+        self.channels.push((path, 0));
+        let channel_id = self.channels.len() as u16;
+
+        let mut channel = Vec::new();
+        channel.extend_from_slice(&channel_id.to_le_bytes());
+        channel.extend_from_slice(&0u16.to_le_bytes()); // Schemaless
+        put_str(&mut channel, &format!("/{path}"));
+        put_str(&mut channel, "json");
+        channel.extend_from_slice(&0u32.to_le_bytes()); // No metadata
+        write_record(&mut self.writer, CHANNEL, &channel)?;
+
+        Ok(channel_id)
+    }
+}
+
+impl<W: Write> Drop for McapWriter<W> {
+    fn drop(&mut self) {
+        self.finish().ok();
+    }
+}
+
+/// Reads an MCAP file and publishes its messages on the bus again.
+///
+/// Channels are matched to topics by their full path, e.g. `/Toplevel/SubTopic/Topic`, and their
+/// messages must be JSON encoded. Chunks are read if they're uncompressed.
+pub struct McapReader<R: Read> {
+    reader: R,
+    /// Records of the chunk being read
+    chunk: Cursor<Vec<u8>>,
+    /// Topic path of each channel id
+    channels: HashMap<u16, String>,
+}
+
+impl McapReader<BufReader<File>> {
+    /// Open the MCAP file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> McapReader<R> {
+    /// Read an MCAP file from `reader`.
+    pub fn new(mut reader: R) -> Result<Self, ReplayError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(ReplayError::NotALog);
+        }
+
+        Ok(Self {
+            reader,
+            chunk: Cursor::new(Vec::new()),
+            channels: HashMap::new(),
+        })
+    }
+
+    /// Read the next message, `None` at the end of the data section.
+    pub fn read_record(&mut self) -> Result<Option<LogRecord>, ReplayError> {
+        loop {
+            // The records of a chunk come before the ones after it
+            let next = match next_record(&mut self.chunk)? {
+                Some(next) => Some(next),
+                None => next_record(&mut self.reader)?,
+            };
+
+            let Some((opcode, record)) = next else {
+                return Ok(None);
+            };
+
+            match opcode {
+                CHANNEL => {
+                    let mut fields = Fields(&record);
+                    let id = fields.u16()?;
+                    let _schema_id = fields.u16()?;
+                    let topic = fields.str()?;
+
+                    if fields.str()? != "json" {
+                        return Err(ReplayError::Unsupported(
+                            "message encodings other than JSON",
+                        ));
+                    }
+
+                    self.channels
+                        .insert(id, topic.trim_start_matches('/').to_string());
+                }
+                MESSAGE => {
+                    let mut fields = Fields(&record);
+                    let channel_id = fields.u16()?;
+                    let _sequence = fields.bytes(4)?;
+                    let log_time = u64::from_le_bytes(fields.bytes(8)?.try_into().unwrap());
+                    let _publish_time = fields.bytes(8)?;
+
+                    let path = self.channels.get(&channel_id).ok_or(ReplayError::NotALog)?;
+
+                    return Ok(Some(LogRecord {
+                        timestamp: UNIX_EPOCH + Duration::from_nanos(log_time),
+                        path: path.clone(),
+                        payload: fields.0.to_vec(),
+                    }));
+                }
+                CHUNK => {
+                    let mut fields = Fields(&record);
+                    let _start_end_times_size_crc = fields.bytes(28)?;
+
+                    if !fields.str()?.is_empty() {
+                        return Err(ReplayError::Unsupported("compressed MCAP chunks"));
+                    }
+
+                    let length = u64::from_le_bytes(fields.bytes(8)?.try_into().unwrap());
+                    let records =
+                        fields.bytes(length.try_into().map_err(|_| ReplayError::NotALog)?)?;
+
+                    self.chunk = Cursor::new(records.to_vec());
+                }
+                DATA_END | FOOTER => return Ok(None),
+                _ => {} // Header, schemas and records which don't affect the messages
+            }
+        }
+    }
+
+    /// Publish the rest of the messages in the file through the publish functions of their
+    /// topics, returns the number of messages published.
+    pub async fn replay<T: Recordable>(
+        &mut self,
+        speed: ReplaySpeed,
+    ) -> Result<usize, ReplayError> {
+        replay_records::<T>(|| self.read_record(), speed).await
+    }
+}
+
+/// Read the next record and its opcode, `None` at the end of `reader`.
+fn next_record(reader: &mut impl Read) -> Result<Option<(u8, Vec<u8>)>, ReplayError> {
+    let mut opcode = [0; 1];
+
+    if reader.read(&mut opcode)? == 0 {
+        return Ok(None);
+    }
+
+    let mut length = [0; 8];
+    reader.read_exact(&mut length)?;
+
+    Ok(Some((
+        opcode[0],
+        read_record(reader, u64::from_le_bytes(length))?,
+    )))
+}
+
+/// Write a record with its opcode and length.
+fn write_record(writer: &mut impl Write, opcode: u8, record: &[u8]) -> io::Result<()> {
+    writer.write_all(&[opcode])?;
+    writer.write_all(&(record.len() as u64).to_le_bytes())?;
+    writer.write_all(record)
+}
+
+/// Append a length-prefixed string.
+fn put_str(record: &mut Vec<u8>, s: &str) {
+    record.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    record.extend_from_slice(s.as_bytes());
+}
+
+/// The fields of a record, read front to back.
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Fields<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], ReplayError> {
+        if self.0.len() < n {
+            return Err(ReplayError::NotALog);
+        }
+
+        let (bytes, rest) = self.0.split_at(n);
+        self.0 = rest;
+
+        Ok(bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, ReplayError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Result<&'a str, ReplayError> {
+        let length = u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()) as usize;
+
+        std::str::from_utf8(self.bytes(length)?).map_err(|_| ReplayError::NotALog)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::{BusStats, TopicStats};
+use std::{fmt::Write, time::SystemTime};
+
+type Metric = (
+    &'static str,
+    &'static str,
+    &'static str,
+    fn(&TopicStats) -> Option<f64>,
+);
+
+const METRICS: [Metric; 4] = [
+    (
+        "message_bus_published_total",
+        "counter",
+        "Messages published on the topic.",
+        |stats| Some(stats.published as f64),
+    ),
+    (
+        "message_bus_subscribers",
+        "gauge",
+        "Current number of subscribers of the topic.",
+        |stats| Some(stats.subscribers as f64),
+    ),
+    (
+        "message_bus_messages_lost_total",
+        "counter",
+        "Messages missed by the topic's subscribers.",
+        |stats| Some(stats.messages_lost as f64),
+    ),
+    (
+        "message_bus_last_published_timestamp_seconds",
+        "gauge",
+        "When the last message was published on the topic, in seconds since the UNIX epoch.",
+        |stats| {
+            let since_epoch = stats.last_published?.duration_since(SystemTime::UNIX_EPOCH);
+            since_epoch
+                .ok()
+                .map(|since_epoch| since_epoch.as_secs_f64())
+        },
+    ),
+];
+
+impl<const N: usize> BusStats<N> {
+    /// Render the statistics in Prometheus text format, labeled with the path of the topics.
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+
+        for (name, kind, help, value) in METRICS {
+            // Writing to a `String` never fails
+            let _ = writeln!(out, "# HELP {name} {help}");
+            let _ = writeln!(out, "# TYPE {name} {kind}");
+
+            for (path, stats) in self.iter() {
+                // Paths are made of identifiers, so they never need escaping
+                if let Some(value) = value(stats) {
+                    let _ = writeln!(out, "{name}{{topic=\"{path}\"}} {value}");
+                }
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{stats::Counters, Full, TopicStats, WouldBlock};
+use async_channel::{bounded, Receiver, Sender, TryRecvError, TrySendError};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    OnceLock,
+};
+
+/// Work-queue topic type used in static storage in codegen.
+///
+/// Each message published on a queue is received by exactly one [`Worker`].
+pub struct Queue<T> {
+    channel: OnceLock<(Sender<T>, Receiver<T>)>,
+    capacity: usize,
+    missed_messages: AtomicU64,
+    /// Messages dropped by `publish_from_isr`
+    dropped: AtomicU64,
+    counters: Counters,
+}
+
+impl<T> Queue<T> {
+    /// Create a new queue.
+    pub const fn new<const CAP: usize>() -> Self {
+        Self {
+            channel: OnceLock::new(),
+            capacity: CAP,
+            missed_messages: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            counters: Counters::new(),
+        }
+    }
+
+    /// Take a worker handle for the queue.
+    ///
+    /// Any number of workers can be taken, each message is handed to one of them.
+    pub fn take_worker(&self) -> Worker<T> {
+        Worker {
+            recv: self.channel().1.clone(),
+        }
+    }
+
+    /// Number of workers of the queue.
+    pub fn receiver_count(&self) -> usize {
+        // Don't count the receiver kept by the queue itself
+        self.channel
+            .get()
+            .map_or(0, |(sender, _)| sender.receiver_count() - 1)
+    }
+
+    /// Checks if the queue has any workers.
+    pub fn has_subscribers(&self) -> bool {
+        self.receiver_count() > 0
+    }
+
+    /// Snapshot of the queue's statistics.
+    pub fn stats(&self) -> TopicStats {
+        self.counters.snapshot(self.receiver_count())
+    }
+
+    /// Publish to the queue, returns the number of workers which can receive the message.
+    ///
+    /// If the queue is full the oldest message is dropped, this is counted in
+    /// [`Queue::messages_lost`].
+    pub fn publish(&self, payload: T) -> usize {
+        // The queue keeps its own receiver, so it's impossible for the channel to close
+        if let Ok(Some(_)) = self.channel().0.force_send(payload) {
+            self.lost();
+        }
+
+        self.counters.published();
+
+        self.receiver_count()
+    }
+
+    /// Publish to a lossless queue, waits until the queue has room for the message.
+    /// Returns the number of workers which can receive the message.
+    pub async fn publish_lossless(&self, payload: T) -> usize {
+        // The queue keeps its own receiver, so it's impossible for the channel to close
+        self.channel().0.send(payload).await.ok();
+        self.counters.published();
+
+        self.receiver_count()
+    }
+
+    /// Try to publish to a lossless queue, fails if the queue has no room for the message.
+    /// Returns the number of workers which can receive the message.
+    pub fn try_publish(&self, payload: T) -> Result<usize, Full<T>> {
+        match self.channel().0.try_send(payload) {
+            Ok(()) => {
+                self.counters.published();
+                Ok(self.receiver_count())
+            }
+            Err(TrySendError::Full(payload)) => Err(Full(payload)),
+            Err(TrySendError::Closed(_)) => unreachable!(), // Impossible to drop the receiver
+        }
+    }
+
+    /// Publish without blocking or allocating, returns the number of workers which can receive
+    /// the message.
+    ///
+    /// Fails if the queue is full, or if no worker has been taken yet as the queue would have to
+    /// allocate.
+    ///
+    /// A worker waiting for a message is woken from the caller, which runs its executor's waker,
+    /// so this is not async-signal-safe if the waker takes locks.
+    pub fn try_publish_nonblocking(&self, payload: T) -> Result<usize, WouldBlock<T>> {
+        let Some((sender, _)) = self.channel.get() else {
+            return Err(WouldBlock(payload));
+        };
+
+        match sender.try_send(payload) {
+            Ok(()) => {
+                self.counters.published();
+                Ok(self.receiver_count())
+            }
+            Err(TrySendError::Full(payload)) => Err(WouldBlock(payload)),
+            Err(TrySendError::Closed(_)) => unreachable!(), // Impossible to drop the receiver
+        }
+    }
+
+    /// Publish from an interrupt or signal handler, see [`Queue::try_publish_nonblocking`].
+    /// Returns the number of workers which can receive the message.
+    ///
+    /// If the message can't be published without blocking it's dropped and counted in
+    /// [`Queue::dropped_publishes`].
+    pub fn publish_from_isr(&self, payload: T) -> usize {
+        self.try_publish_nonblocking(payload).unwrap_or_else(|_| {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            0
+        })
+    }
+
+    /// Number of messages dropped by [`Queue::publish_from_isr`] since last time this was called.
+    pub fn dropped_publishes(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    /// Number of messages dropped from the queue since last time this was called.
+    pub fn messages_lost(&self) -> u64 {
+        self.missed_messages.swap(0, Ordering::Relaxed)
+    }
+
+    fn lost(&self) {
+        self.missed_messages.fetch_add(1, Ordering::Relaxed);
+        self.counters.lost(1);
+    }
+
+    fn channel(&self) -> &(Sender<T>, Receiver<T>) {
+        self.channel.get_or_init(|| bounded(self.capacity))
+    }
+}
+
+/// A worker on a work-queue topic on the bus.
+#[derive(Clone)]
+pub struct Worker<T> {
+    recv: Receiver<T>,
+}
+
+impl<T> Worker<T> {
+    /// Tries to receive a value, will return `None` if there are none.
+    pub fn try_recv(&mut self) -> Option<T> {
+        match self.recv.try_recv() {
+            Ok(v) => Some(v),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Closed) => unreachable!(), // Impossible to drop the sender
+        }
+    }
+
+    /// Receive a value from the queue.
+    pub async fn recv(&mut self) -> T {
+        match self.recv.recv().await {
+            Ok(msg) => msg,
+            Err(_) => unreachable!(), // Impossible to drop the sender
+        }
+    }
+
+    /// Checks if there is a message in the queue.
+    pub fn is_empty(&self) -> bool {
+        self.recv.is_empty()
+    }
+}
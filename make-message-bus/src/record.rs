@@ -0,0 +1,277 @@
+use crate::{backend, PublishReport, Subscriber};
+use std::{
+    fmt,
+    fs::File,
+    future::Future,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Magic bytes at the start of every log.
+const MAGIC: &[u8; 8] = b"MBUSLOG1";
+
+/// A sub-topic which can be recorded and replayed, implemented by the generated sub-topic enums.
+///
+/// Payloads are serialized as JSON, so every payload on the bus must implement `Serialize` and
+/// `Deserialize`.
+pub trait Recordable: Sized {
+    /// Full path of the topic the message is published on, e.g. `Toplevel/SubTopic/Topic`.
+    fn path(&self) -> &'static str;
+
+    /// Serialize the payload of the message.
+    fn encode_payload(&self) -> serde_json::Result<Vec<u8>>;
+
+    /// Deserialize a message from the full path of its topic and its serialized payload.
+    fn decode(path: &str, payload: &[u8]) -> Result<Self, ReplayError>;
+
+    /// Publish the message on its topic, and on the topic's parent sub-topics.
+    fn publish(self) -> impl Future<Output = PublishReport> + Send;
+}
+
+/// A message read from a log.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogRecord {
+    /// When the message was recorded.
+    pub timestamp: SystemTime,
+    /// Full path of the topic, e.g. `Toplevel/SubTopic/Topic`.
+    pub path: String,
+    /// The payload serialized as JSON.
+    pub payload: Vec<u8>,
+}
+
+/// Writes the messages received on a sub-topic to a log.
+///
+/// The log starts with the magic bytes `MBUSLOG1`, followed by one record per message:
+///
+/// - the time it was recorded, in nanoseconds since the UNIX epoch, as a little endian `u64`,
+/// - the length of the rest of the record, as a little endian `u32`,
+/// - the length of the topic path, as a little endian `u16`,
+/// - the full path of the topic, e.g. `Toplevel/SubTopic/Topic`,
+/// - the payload serialized as JSON.
+pub struct Recorder<W: Write> {
+    writer: W,
+}
+
+impl Recorder<BufWriter<File>> {
+    /// Create the log file at `path`, truncating it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    /// Start a log in `writer`.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+
+        Ok(Self { writer })
+    }
+
+    /// Write a message, timestamped now.
+    pub fn write<T: Recordable>(&mut self, message: &T) -> io::Result<()> {
+        let path = message.path().as_bytes();
+        let payload = message.encode_payload()?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64;
+
+        let length = u32::try_from(2 + path.len() + payload.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "the message is too large"))?;
+
+        self.writer.write_all(&timestamp.to_le_bytes())?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&(path.len() as u16).to_le_bytes())?;
+        self.writer.write_all(path)?;
+        self.writer.write_all(&payload)
+    }
+
+    /// Write every message received by `subscriber`, only returns if writing fails.
+    ///
+    /// The log is buffered by the writer, it's flushed when the recorder is dropped.
+    pub async fn record<T: Recordable + Clone>(
+        &mut self,
+        mut subscriber: Subscriber<T>,
+    ) -> io::Result<()> {
+        loop {
+            let message = subscriber.recv().await;
+
+            self.write(&message)?;
+        }
+    }
+
+    /// Flush the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Get the writer back.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// How fast a log is replayed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReplaySpeed {
+    /// Keep the time between the messages as they were recorded.
+    #[default]
+    Original,
+    /// Publish the messages back-to-back.
+    AsFastAsPossible,
+}
+
+/// Reads a log written by a [`Recorder`] and publishes its messages on the bus again.
+pub struct Replayer<R: Read> {
+    reader: R,
+}
+
+impl Replayer<BufReader<File>> {
+    /// Open the log file at `path`.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ReplayError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Replayer<R> {
+    /// Read a log from `reader`.
+    pub fn new(mut reader: R) -> Result<Self, ReplayError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+            return Err(ReplayError::NotALog);
+        }
+
+        Ok(Self { reader })
+    }
+
+    /// Read the next record, `None` at the end of the log.
+    pub fn read_record(&mut self) -> Result<Option<LogRecord>, ReplayError> {
+        let mut timestamp = [0; 8];
+
+        // The log may end between records, but not within one
+        match self.reader.read(&mut timestamp)? {
+            0 => return Ok(None),
+            n => self.reader.read_exact(&mut timestamp[n..])?,
+        }
+
+        let mut length = [0; 4];
+        self.reader.read_exact(&mut length)?;
+
+        let record = read_record(&mut self.reader, u32::from_le_bytes(length).into())?;
+
+        let (path_length, rest) = record
+            .split_first_chunk::<2>()
+            .ok_or(ReplayError::NotALog)?;
+        let path_length = u16::from_le_bytes(*path_length) as usize;
+
+        if rest.len() < path_length {
+            return Err(ReplayError::NotALog);
+        }
+
+        let (path, payload) = rest.split_at(path_length);
+
+        Ok(Some(LogRecord {
+            timestamp: UNIX_EPOCH + Duration::from_nanos(u64::from_le_bytes(timestamp)),
+            path: String::from_utf8(path.to_vec()).map_err(|_| ReplayError::NotALog)?,
+            payload: payload.to_vec(),
+        }))
+    }
+
+    /// Publish the rest of the messages in the log through the publish functions of their topics,
+    /// returns the number of messages published.
+    pub async fn replay<T: Recordable>(
+        &mut self,
+        speed: ReplaySpeed,
+    ) -> Result<usize, ReplayError> {
+        replay_records::<T>(|| self.read_record(), speed).await
+    }
+}
+
+/// Read a record of `length` bytes.
+///
+/// The length is read from the log, so the buffer grows as the record is read instead of being
+/// allocated upfront, a corrupt length fails at the end of the log.
+pub(crate) fn read_record(reader: &mut impl Read, length: u64) -> io::Result<Vec<u8>> {
+    let mut record = Vec::new();
+    reader.take(length).read_to_end(&mut record)?;
+
+    if (record.len() as u64) < length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(record)
+}
+
+/// Publish the records from `next_record` until it returns `None`, returns the number of messages
+/// published.
+pub(crate) async fn replay_records<T: Recordable>(
+    mut next_record: impl FnMut() -> Result<Option<LogRecord>, ReplayError>,
+    speed: ReplaySpeed,
+) -> Result<usize, ReplayError> {
+    let mut start: Option<(SystemTime, Instant)> = None;
+    let mut count = 0;
+
+    while let Some(record) = next_record()? {
+        let message = T::decode(&record.path, &record.payload)?;
+
+        if speed == ReplaySpeed::Original {
+            let (first, started) = *start.get_or_insert((record.timestamp, Instant::now()));
+            let offset = record.timestamp.duration_since(first).unwrap_or_default();
+
+            backend::sleep((started + offset).saturating_duration_since(Instant::now())).await;
+        }
+
+        message.publish().await;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Errors when replaying a log.
+#[derive(Debug)]
+pub enum ReplayError {
+    /// Reading the log failed.
+    Io(io::Error),
+    /// The log is not in the expected format, or is corrupt.
+    NotALog,
+    /// The log uses a feature of its format which is not supported.
+    Unsupported(&'static str),
+    /// The log has a message for a topic which is not on the bus.
+    UnknownTopic(String),
+    /// The payload of a message could not be deserialized.
+    Payload(serde_json::Error),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(err) => write!(f, "reading the log failed: {err}"),
+            ReplayError::NotALog => {
+                f.write_str("not a log in the expected format, or the log is corrupt")
+            }
+            ReplayError::Unsupported(feature) => write!(f, "{feature} are not supported"),
+            ReplayError::UnknownTopic(path) => write!(f, "the topic `{path}` is not on the bus"),
+            ReplayError::Payload(err) => write!(f, "the payload could not be deserialized: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplayError::Io(err) => Some(err),
+            ReplayError::Payload(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReplayError {
+    fn from(err: io::Error) -> Self {
+        ReplayError::Io(err)
+    }
+}
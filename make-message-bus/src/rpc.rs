@@ -0,0 +1,183 @@
+use crate::backend;
+use async_channel::{bounded, Receiver, Sender};
+use core::fmt;
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+type Channel<Req, Resp> = (Sender<Request<Req, Resp>>, Receiver<Request<Req, Resp>>);
+
+/// Request/response topic type used in static storage in codegen.
+pub struct Rpc<Req, Resp> {
+    channel: OnceLock<Channel<Req, Resp>>,
+    capacity: usize,
+    servers: AtomicUsize,
+}
+
+impl<Req, Resp> Rpc<Req, Resp> {
+    /// Create a new request/response topic.
+    pub const fn new<const CAP: usize>() -> Self {
+        Self {
+            channel: OnceLock::new(),
+            capacity: CAP,
+            servers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Send a request and wait for the response.
+    pub async fn request(&self, request: Req) -> Result<Resp, RpcError> {
+        if self.servers.load(Ordering::SeqCst) == 0 {
+            return Err(RpcError::NoServer);
+        }
+
+        let (reply, response) = bounded(1);
+        let (send, recv) = self.channel();
+
+        // The topic keeps its own receiver, so it's impossible for the channel to close
+        send.send(Request {
+            payload: request,
+            reply: Reply(reply),
+        })
+        .await
+        .ok();
+
+        // The last responder may have dropped and drained the queue before the request was
+        // enqueued, drop the queued requests here instead so the request doesn't wait forever
+        if self.servers.load(Ordering::SeqCst) == 0 {
+            while recv.try_recv().is_ok() {}
+        }
+
+        response.recv().await.map_err(|_| RpcError::NoResponse)
+    }
+
+    /// Send a request and wait for the response, giving up after `timeout`.
+    pub async fn request_timeout(&self, request: Req, timeout: Duration) -> Result<Resp, RpcError> {
+        with_timeout(self.request(request), timeout).await
+    }
+
+    /// Take a responder which serves requests on the topic.
+    ///
+    /// Any number of responders can be taken, each request is handed to one of them.
+    pub fn take_responder(&'static self) -> Responder<Req, Resp> {
+        self.servers.fetch_add(1, Ordering::SeqCst);
+
+        Responder {
+            recv: self.channel().1.clone(),
+            rpc: self,
+        }
+    }
+
+    fn channel(&self) -> &Channel<Req, Resp> {
+        self.channel.get_or_init(|| bounded(self.capacity))
+    }
+}
+
+async fn with_timeout<Resp>(
+    request: impl Future<Output = Result<Resp, RpcError>>,
+    timeout: Duration,
+) -> Result<Resp, RpcError> {
+    backend::timeout(timeout, request)
+        .await
+        .unwrap_or(Err(RpcError::Timeout))
+}
+
+/// Errors when making a request on a request/response topic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RpcError {
+    /// There is no responder serving the topic.
+    NoServer,
+    /// The responder dropped the request without responding.
+    NoResponse,
+    /// No response was received in time.
+    Timeout,
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RpcError::NoServer => f.write_str("no responder is serving the topic"),
+            RpcError::NoResponse => f.write_str("the request was dropped without a response"),
+            RpcError::Timeout => f.write_str("the request timed out"),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {}
+
+/// A request received by a [`Responder`].
+pub struct Request<Req, Resp> {
+    payload: Req,
+    reply: Reply<Resp>,
+}
+
+impl<Req, Resp> Request<Req, Resp> {
+    /// The payload of the request.
+    pub fn payload(&self) -> &Req {
+        &self.payload
+    }
+
+    /// Split the request into its payload and the handle used to respond.
+    pub fn into_parts(self) -> (Req, Reply<Resp>) {
+        (self.payload, self.reply)
+    }
+
+    /// Respond to the request.
+    pub fn respond(self, response: Resp) {
+        self.reply.send(response);
+    }
+}
+
+/// Handle used to respond to a [`Request`].
+pub struct Reply<Resp>(Sender<Resp>);
+
+impl<Resp> Reply<Resp> {
+    /// Send the response, if the requester has given up waiting the response is dropped.
+    pub fn send(self, response: Resp) {
+        self.0.try_send(response).ok();
+    }
+}
+
+/// A responder serving requests on a request/response topic on the bus.
+pub struct Responder<Req: 'static, Resp: 'static> {
+    recv: Receiver<Request<Req, Resp>>,
+    rpc: &'static Rpc<Req, Resp>,
+}
+
+impl<Req, Resp> Responder<Req, Resp> {
+    /// Receive a request.
+    ///
+    /// Requests where the requester has already given up waiting for the response are skipped.
+    pub async fn recv(&mut self) -> Request<Req, Resp> {
+        loop {
+            match self.recv.recv().await {
+                Ok(request) if request.reply.0.receiver_count() == 0 => {} // Skip abandoned requests
+                Ok(request) => return request,
+                Err(_) => unreachable!(), // Impossible to drop the sender
+            }
+        }
+    }
+
+    /// Serve requests with `f` forever.
+    pub async fn serve(mut self, mut f: impl FnMut(Req) -> Resp) {
+        loop {
+            let (payload, reply) = self.recv().await.into_parts();
+
+            reply.send(f(payload));
+        }
+    }
+}
+
+impl<Req, Resp> Drop for Responder<Req, Resp> {
+    fn drop(&mut self) {
+        if self.rpc.servers.fetch_sub(1, Ordering::SeqCst) == 1 {
+            // The last responder is gone, drop the queued requests so requesters are not left
+            // waiting for a response
+            while self.recv.try_recv().is_ok() {}
+        }
+    }
+}
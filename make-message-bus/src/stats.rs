@@ -0,0 +1,51 @@
+use crate::TopicStats;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Statistics counters of a topic, updated without locking.
+pub(crate) struct Counters {
+    published: AtomicU64,
+    lost: AtomicU64,
+    /// Nanoseconds since the UNIX epoch of the last publish, 0 if never published
+    last_published: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) const fn new() -> Self {
+        Self {
+            published: AtomicU64::new(0),
+            lost: AtomicU64::new(0),
+            last_published: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a published message.
+    pub(crate) fn published(&self) {
+        self.published.fetch_add(1, Ordering::Relaxed);
+
+        let now = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_nanos() as u64);
+
+        self.last_published.store(now, Ordering::Relaxed);
+    }
+
+    /// Count messages missed by a subscriber.
+    pub(crate) fn lost(&self, count: u64) {
+        self.lost.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self, subscribers: usize) -> TopicStats {
+        let last_published = match self.last_published.load(Ordering::Relaxed) {
+            0 => None,
+            nanos => Some(SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos)),
+        };
+
+        TopicStats {
+            published: self.published.load(Ordering::Relaxed),
+            subscribers,
+            messages_lost: self.lost.load(Ordering::Relaxed),
+            last_published,
+        }
+    }
+}
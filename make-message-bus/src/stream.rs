@@ -0,0 +1,95 @@
+use crate::{
+    backend::{Lagged, RecvStream},
+    stats::Counters,
+    topic::SpaceRef,
+    LagPolicy, LagReporter, Subscriber,
+};
+use core::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use futures_core::Stream;
+use std::collections::VecDeque;
+
+/// A subscriber to a topic on the bus, adapted into a [`Stream`].
+///
+/// Created by [`Subscriber::into_stream`].
+pub struct SubscriberStream<T: Clone + 'static> {
+    recv: RecvStream<T>,
+    pending: VecDeque<T>,
+    missed_messages: u64,
+    lag_policy: LagPolicy,
+    reporter: LagReporter,
+    counters: &'static Counters,
+    // Declared after `recv` so it's dropped after it
+    space: SpaceRef,
+}
+
+impl<T> Subscriber<T>
+where
+    T: Clone + Send + 'static,
+{
+    /// Convert the subscriber into a [`Stream`] of the messages on the topic.
+    ///
+    /// Lagging is handled the same way as in [`Subscriber::recv`], the stream skips missed
+    /// messages and counts them in [`SubscriberStream::messages_lost`], reports them on the
+    /// dead-letter topic, and panics with [`LagPolicy::Panic`].
+    pub fn into_stream(self) -> SubscriberStream<T> {
+        SubscriberStream {
+            recv: self.recv.into_stream(),
+            pending: self.pending,
+            missed_messages: self.missed_messages,
+            lag_policy: self.lag_policy,
+            reporter: self.reporter,
+            counters: self.counters,
+            space: self.space,
+        }
+    }
+}
+
+impl<T> SubscriberStream<T>
+where
+    T: Clone,
+{
+    /// Number of messages missed since last time this was called.
+    pub fn messages_lost(&mut self) -> u64 {
+        let n = self.missed_messages;
+        self.missed_messages = 0;
+
+        n
+    }
+}
+
+// The buffered payloads are never pinned, so the stream can move even if they aren't `Unpin`,
+// which `VecDeque<T>` would otherwise require
+impl<T: Clone> Unpin for SubscriberStream<T> {}
+
+impl<T> Stream for SubscriberStream<T>
+where
+    T: Clone + Send + 'static,
+{
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Some(msg) = self.pending.pop_front() {
+            return Poll::Ready(Some(msg));
+        }
+
+        loop {
+            match ready!(self.recv.poll_recv(cx)) {
+                Ok(msg) => {
+                    self.space.0.notify();
+                    return Poll::Ready(Some(msg));
+                }
+                Err(Lagged(n)) => {
+                    self.counters.lost(n);
+                    self.reporter.report(n);
+
+                    let this = &mut *self;
+                    // A stream has no way to report the lag as an error
+                    let _ = this.lag_policy.lagged(n, &mut this.missed_messages);
+                }
+            }
+        }
+    }
+}
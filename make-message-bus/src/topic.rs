@@ -0,0 +1,562 @@
+use crate::{
+    backend::{self, block_on, Lagged, Receiver, Sender, TryRecvError},
+    stats::Counters,
+    DroppedMessages, Full, LagPolicy, LagReporter, TopicStats,
+};
+use event_listener::Event;
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Mutex, MutexGuard, OnceLock, PoisonError},
+    time::{Duration, Instant},
+};
+// Publishing from interrupts needs a try-lock path, which the `tokio` and `async-broadcast`
+// channels don't have
+#[cfg(not(any(feature = "tokio", feature = "async-broadcast")))]
+use {
+    crate::WouldBlock,
+    std::sync::{
+        atomic::{AtomicU64, Ordering},
+        TryLockError,
+    },
+};
+
+/// Topic type used in static storage in codegen.
+///
+/// Holds up to `CAP` messages per subscriber and allows up to `SUBS` subscribers at a time.
+pub struct Topic<T: Clone, const CAP: usize, const SUBS: usize> {
+    sender: OnceLock<Sender<T>>,
+    // Serializes publishing on retained, lossless and sequenced topics
+    state: Mutex<State<T>>,
+    retain: usize,
+    pub(crate) sequence: Option<fn(&mut T, u64)>,
+    space: Space,
+    #[cfg(not(any(feature = "tokio", feature = "async-broadcast")))]
+    dropped: AtomicU64,
+    path: Option<&'static str>,
+    dead_letters: Option<fn(DroppedMessages)>,
+    counters: Counters,
+}
+
+struct State<T> {
+    retained: VecDeque<T>,
+    next_seq: u64,
+}
+
+impl<T, const CAP: usize, const SUBS: usize> Topic<T, CAP, SUBS>
+where
+    T: Clone,
+{
+    /// Create a new topic.
+    pub const fn new() -> Self {
+        Self {
+            sender: OnceLock::new(),
+            state: Mutex::new(State {
+                retained: VecDeque::new(),
+                next_seq: 0,
+            }),
+            retain: 0,
+            sequence: None,
+            space: Space {
+                lossless: false,
+                event: Event::new(),
+            },
+            #[cfg(not(any(feature = "tokio", feature = "async-broadcast")))]
+            dropped: AtomicU64::new(0),
+            path: None,
+            dead_letters: None,
+            counters: Counters::new(),
+        }
+    }
+
+    /// Retain the last `count` published messages.
+    pub const fn retain(mut self, count: usize) -> Self {
+        self.retain = count;
+        self
+    }
+
+    /// Make the topic lossless, see [`Topic::publish_lossless`].
+    ///
+    /// This only marks the topic so receiving subscribers wake waiting publishers, the topic still
+    /// has to be published to via [`Topic::publish_lossless`] or [`Topic::try_publish`].
+    pub const fn lossless(mut self) -> Self {
+        self.space.lossless = true;
+        self
+    }
+
+    /// Set the path of the topic, e.g. `Toplevel/SubTopic/Topic`, used when reporting messages
+    /// missed by the subscribers.
+    pub const fn path(mut self, path: &'static str) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    /// Report messages missed by the subscribers with `report`.
+    pub const fn dead_letters(mut self, report: fn(DroppedMessages)) -> Self {
+        self.dead_letters = Some(report);
+        self
+    }
+
+    fn reporter(&self) -> LagReporter {
+        LagReporter {
+            topic: self.path,
+            subscriber: None,
+            dead_letters: self.dead_letters,
+        }
+    }
+
+    /// Subscribe to the topic.
+    ///
+    /// # Panics
+    ///
+    /// If the topic already has `SUBS` subscribers.
+    pub fn subscribe(&'static self) -> Subscriber<T> {
+        if SUBS == usize::MAX {
+            return Subscriber::new(self, VecDeque::new());
+        }
+
+        // Hold the lock while subscribing so the number of subscribers can't change in-between
+        let _state = self.lock_state();
+
+        Subscriber::new(self, VecDeque::new())
+    }
+
+    /// Subscribe to the topic, the subscriber will first receive the latest retained message if
+    /// there is one.
+    pub fn subscribe_with_latest(&'static self) -> Subscriber<T> {
+        // Hold the lock while subscribing so no publish can happen in-between
+        let state = self.lock_state();
+
+        Subscriber::new(self, state.retained.back().cloned().into_iter().collect())
+    }
+
+    /// Subscribe to the topic, the subscriber will first receive all retained messages, oldest
+    /// first.
+    pub fn subscribe_with_history(&'static self) -> Subscriber<T> {
+        // Hold the lock while subscribing so no publish can happen in-between
+        let state = self.lock_state();
+
+        Subscriber::new(self, state.retained.clone())
+    }
+
+    /// Get the latest retained message, if any.
+    pub fn latest(&self) -> Option<T> {
+        self.lock_state().retained.back().cloned()
+    }
+
+    /// Number of subscribers of the topic.
+    pub fn receiver_count(&self) -> usize {
+        // Don't initialize the channel only to find out that nobody is subscribed
+        self.sender.get().map_or(0, Sender::receiver_count)
+    }
+
+    /// Checks if the topic has any subscribers.
+    pub fn has_subscribers(&self) -> bool {
+        self.receiver_count() > 0
+    }
+
+    /// Snapshot of the topic's statistics.
+    pub fn stats(&self) -> TopicStats {
+        self.counters.snapshot(self.receiver_count())
+    }
+
+    /// Count a message published to a sub-topic without subscribers, which is skipped instead of
+    /// wrapping the payload for nobody.
+    pub fn count_publish(&self) {
+        self.counters.published();
+    }
+
+    /// Publish to a topic, returns the number of subscribers which received the message.
+    pub fn publish(&self, payload: T) -> usize {
+        if self.retain == 0 && self.sequence.is_none() {
+            self.counters.published();
+            return self.sender().send(payload);
+        }
+
+        let mut state = self.lock_state();
+        self.publish_locked(&mut state, payload)
+    }
+
+    /// Publish to a lossless topic, waits until all subscribers have room for the message.
+    /// Returns the number of subscribers which received the message.
+    pub async fn publish_lossless(&self, mut payload: T) -> usize {
+        loop {
+            // Start listening before trying, so no notification can be missed in-between
+            let listener = self.space.event.listen();
+
+            match self.try_publish(payload) {
+                Ok(receivers) => return receivers,
+                Err(Full(p)) => payload = p,
+            }
+
+            listener.await;
+        }
+    }
+
+    /// Try to publish to a lossless topic, fails if any subscriber has no room for the message.
+    /// Returns the number of subscribers which received the message.
+    pub fn try_publish(&self, payload: T) -> Result<usize, Full<T>> {
+        let mut state = self.lock_state();
+
+        if self.sender().len() >= CAP {
+            return Err(Full(payload));
+        }
+
+        Ok(self.publish_locked(&mut state, payload))
+    }
+
+    fn publish_locked(&self, state: &mut State<T>, mut payload: T) -> usize {
+        self.counters.published();
+
+        if let Some(set_seq) = self.sequence {
+            set_seq(&mut payload, state.next_seq);
+            state.next_seq += 1;
+        }
+
+        if self.retain > 0 {
+            self.retain_locked(state, payload.clone());
+        }
+
+        self.sender().send(payload)
+    }
+
+    fn retain_locked(&self, state: &mut State<T>, payload: T) {
+        if state.retained.len() == self.retain {
+            state.retained.pop_front();
+        } else if state.retained.capacity() == 0 {
+            // Allocate once, so publishing from interrupts can retain without allocating
+            state.retained.reserve_exact(self.retain);
+        }
+
+        state.retained.push_back(payload);
+    }
+
+    fn sender(&self) -> &Sender<T> {
+        self.sender.get_or_init(|| Sender::new(CAP))
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, State<T>> {
+        // The state is always consistent, so a poisoned lock is of no concern
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(not(any(feature = "tokio", feature = "async-broadcast")))]
+impl<T, const CAP: usize, const SUBS: usize> Topic<T, CAP, SUBS>
+where
+    T: Clone,
+{
+    /// Publish without blocking or allocating, returns the number of subscribers which received
+    /// the message.
+    ///
+    /// Fails if another publisher holds the topic, if a lossless topic has no room for the
+    /// message, or if retaining the message would allocate.
+    ///
+    /// Only available on the `blocking` backend, the `tokio` and `async-broadcast` channels always
+    /// take their lock. Subscribers waiting for a message are woken by the backend's timer thread,
+    /// as their executors' wakers could take locks of their own.
+    pub fn try_publish_nonblocking(&self, mut payload: T) -> Result<usize, WouldBlock<T>> {
+        if self.retain == 0 && self.sequence.is_none() && !self.space.lossless {
+            return self.try_send(payload);
+        }
+
+        let mut state = match self.state.try_lock() {
+            Ok(state) => state,
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(TryLockError::WouldBlock) => return Err(WouldBlock(payload)),
+        };
+
+        if self.space.lossless {
+            // The channel is locked by someone else if its length isn't available
+            match self.sender.get().map_or(Some(0), Sender::try_len) {
+                Some(len) if len < CAP => {}
+                _ => return Err(WouldBlock(payload)),
+            }
+        }
+
+        let retain_allocates =
+            state.retained.len() < self.retain && state.retained.len() == state.retained.capacity();
+
+        if retain_allocates {
+            return Err(WouldBlock(payload));
+        }
+
+        // Only commit the sequence number and retained message once the message is sent
+        if let Some(set_seq) = self.sequence {
+            set_seq(&mut payload, state.next_seq);
+        }
+
+        let retained = (self.retain > 0).then(|| payload.clone());
+        let receivers = self.try_send(payload)?;
+
+        if self.sequence.is_some() {
+            state.next_seq += 1;
+        }
+
+        if let Some(retained) = retained {
+            self.retain_locked(&mut state, retained);
+        }
+
+        Ok(receivers)
+    }
+
+    /// Publish from an interrupt or signal handler, see [`Topic::try_publish_nonblocking`].
+    /// Returns the number of subscribers which received the message.
+    ///
+    /// If the message can't be published without blocking it's dropped and counted in
+    /// [`Topic::dropped_publishes`].
+    pub fn publish_from_isr(&self, payload: T) -> usize {
+        self.try_publish_nonblocking(payload).unwrap_or_else(|_| {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            0
+        })
+    }
+
+    /// Number of messages dropped by [`Topic::publish_from_isr`] since last time this was called.
+    pub fn dropped_publishes(&self) -> u64 {
+        self.dropped.swap(0, Ordering::Relaxed)
+    }
+
+    fn try_send(&self, payload: T) -> Result<usize, WouldBlock<T>> {
+        let receivers = match self.sender.get() {
+            Some(sender) => sender.try_send(payload).map_err(WouldBlock)?,
+            // Nobody has subscribed, don't allocate the channel
+            None => 0,
+        };
+
+        self.counters.published();
+
+        Ok(receivers)
+    }
+}
+
+impl<T, const CAP: usize, const SUBS: usize> Default for Topic<T, CAP, SUBS>
+where
+    T: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Wakes publishers waiting for room on lossless topics.
+pub(crate) struct Space {
+    lossless: bool,
+    event: Event,
+}
+
+impl Space {
+    /// A subscriber has received a message, wake publishers waiting for space on lossless topics.
+    pub(crate) fn notify(&self) {
+        if self.lossless {
+            self.event.notify(usize::MAX);
+        }
+    }
+}
+
+/// Reference to the space of a subscriber's topic.
+pub(crate) struct SpaceRef(pub(crate) &'static Space);
+
+impl Drop for SpaceRef {
+    fn drop(&mut self) {
+        // The subscriber's receiver is dropped first, freeing its space on lossless topics
+        self.0.notify();
+    }
+}
+
+/// A subscriber to a topic on the bus.
+pub struct Subscriber<T: 'static> {
+    pub(crate) recv: Receiver<T>,
+    pub(crate) pending: VecDeque<T>,
+    pub(crate) missed_messages: u64,
+    pub(crate) lag_policy: LagPolicy,
+    pub(crate) reporter: LagReporter,
+    pub(crate) counters: &'static Counters,
+    // Declared after `recv` so it's dropped after it
+    pub(crate) space: SpaceRef,
+}
+
+impl<T> Subscriber<T>
+where
+    T: Clone,
+{
+    fn new<const CAP: usize, const SUBS: usize>(
+        topic: &'static Topic<T, CAP, SUBS>,
+        pending: VecDeque<T>,
+    ) -> Self {
+        let sender = topic.sender();
+
+        assert!(
+            sender.receiver_count() < SUBS,
+            "the topic already has the maximum number of subscribers"
+        );
+
+        Self {
+            recv: sender.subscribe(),
+            pending,
+            missed_messages: 0,
+            lag_policy: LagPolicy::Skip,
+            reporter: topic.reporter(),
+            counters: &topic.counters,
+            space: SpaceRef(&topic.space),
+        }
+    }
+
+    /// Name the subscriber, reported on the dead-letter topic when it misses messages.
+    pub fn with_name(mut self, name: &'static str) -> Self {
+        self.reporter.subscriber = Some(name);
+        self
+    }
+
+    /// Set what the subscriber does when it lags behind, [`LagPolicy::Skip`] by default.
+    pub fn with_lag_policy(mut self, lag_policy: LagPolicy) -> Self {
+        self.lag_policy = lag_policy;
+        self
+    }
+
+    /// Tries to receive a value, will return `None` if there are none.
+    pub fn try_recv(&mut self) -> Option<T> {
+        if let Some(msg) = self.pending.pop_front() {
+            return Some(msg);
+        }
+
+        loop {
+            match self.recv.try_recv() {
+                Ok(v) => {
+                    self.space.0.notify();
+                    return Some(v);
+                }
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Lagged(n)) => {
+                    // Only `recv_checked` reports the lag as an error
+                    let _ = self.lagged(n);
+                }
+            }
+        }
+    }
+
+    /// Receive a value from the bus.
+    pub async fn recv(&mut self) -> T {
+        loop {
+            if let Ok(msg) = self.recv_checked().await {
+                return msg;
+            }
+        }
+    }
+
+    /// Receive a value from the bus, returns `Err(Lagged(n))` if `n` messages were missed and
+    /// the subscriber has [`LagPolicy::Error`].
+    ///
+    /// The next call continues from the oldest message still on the topic.
+    pub async fn recv_checked(&mut self) -> Result<T, Lagged> {
+        if let Some(msg) = self.pending.pop_front() {
+            return Ok(msg);
+        }
+
+        loop {
+            match self.recv.recv().await {
+                Ok(msg) => {
+                    self.space.0.notify();
+                    return Ok(msg);
+                }
+                Err(Lagged(n)) => self.lagged(n)?,
+            }
+        }
+    }
+
+    /// Receive a value from the bus, giving up after `timeout`.
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Result<T, Timeout> {
+        backend::timeout(timeout, self.recv()).await.ok_or(Timeout)
+    }
+
+    /// Receive a value from the bus, giving up at `deadline`.
+    pub async fn recv_deadline(&mut self, deadline: Instant) -> Result<T, Timeout> {
+        self.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            .await
+    }
+
+    /// Receive up to `max` values into `buffer`, waits for the first value and then takes the
+    /// ones already published. Returns the number of values received.
+    pub async fn recv_many(&mut self, buffer: &mut Vec<T>, max: usize) -> usize {
+        self.recv_many_map(buffer, max, |msg| msg).await
+    }
+
+    /// [`Subscriber::recv_many`] with `map` applied to the values before they're buffered.
+    pub(crate) async fn recv_many_map<U>(
+        &mut self,
+        buffer: &mut Vec<U>,
+        max: usize,
+        map: impl Fn(T) -> U,
+    ) -> usize {
+        if max == 0 {
+            return 0;
+        }
+
+        buffer.push(map(self.recv().await));
+        let mut received = 1;
+
+        while received < max {
+            match self.try_recv() {
+                Some(msg) => buffer.push(map(msg)),
+                None => break,
+            }
+
+            received += 1;
+        }
+
+        received
+    }
+
+    /// Receive a value from the bus, parking the thread until there is one.
+    ///
+    /// For threads without an async runtime, must not be called from async code as it blocks the
+    /// executor.
+    pub fn blocking_recv(&mut self) -> T {
+        match block_on(self.recv(), None) {
+            Some(msg) => msg,
+            None => unreachable!(), // There is no deadline
+        }
+    }
+
+    /// Receive a value from the bus, parking the thread until there is one or `timeout` has
+    /// passed, then `Err(Timeout)` is returned.
+    ///
+    /// For threads without an async runtime, must not be called from async code as it blocks the
+    /// executor.
+    pub fn blocking_recv_timeout(&mut self, timeout: Duration) -> Result<T, Timeout> {
+        block_on(self.recv(), Some(Instant::now() + timeout)).ok_or(Timeout)
+    }
+
+    /// Checks if there is a message on the topic.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty() && self.recv.is_empty()
+    }
+
+    fn lagged(&mut self, n: u64) -> Result<(), Lagged> {
+        self.counters.lost(n);
+
+        self.reporter.report(n);
+
+        self.lag_policy.lagged(n, &mut self.missed_messages)
+    }
+
+    /// Number of messages missed since last time this was called.
+    pub fn messages_lost(&mut self) -> u64 {
+        let n = self.missed_messages;
+        self.missed_messages = 0;
+
+        n
+    }
+}
+
+/// Error when no message was received in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Timeout;
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("no message was received in time")
+    }
+}
+
+impl std::error::Error for Timeout {}
@@ -0,0 +1,56 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::{make_message_bus, Timeout};
+use std::{thread, time::Duration};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values => u32,
+        Lagging [2] => u32,
+        Timeout => u32,
+    },
+);
+
+#[test]
+fn blocking_recv_parks_until_published() {
+    let mut sub = bus::Values::subscribe();
+
+    let publisher = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(50));
+        bus::Values::publish(1);
+    });
+
+    assert_eq!(sub.blocking_recv(), 1);
+    publisher.join().unwrap();
+}
+
+#[test]
+fn blocking_recv_counts_lagged_messages() {
+    let mut sub = bus::Lagging::subscribe();
+
+    for i in 0..5 {
+        bus::Lagging::publish(i);
+    }
+
+    assert_eq!(sub.blocking_recv(), 3);
+    assert_eq!(sub.blocking_recv_timeout(Duration::ZERO), Ok(4));
+    assert_eq!(sub.messages_lost(), 3);
+}
+
+#[test]
+fn blocking_recv_timeout_gives_up() {
+    let mut sub = bus::Timeout::subscribe();
+
+    assert_eq!(
+        sub.blocking_recv_timeout(Duration::from_millis(20)),
+        Err(Timeout)
+    );
+
+    let publisher = thread::spawn(|| {
+        thread::sleep(Duration::from_millis(20));
+        bus::Timeout::publish(2);
+    });
+
+    assert_eq!(sub.blocking_recv_timeout(Duration::from_secs(5)), Ok(2));
+    publisher.join().unwrap();
+}
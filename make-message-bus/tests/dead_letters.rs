@@ -0,0 +1,46 @@
+use make_message_bus::{make_message_bus, DroppedMessages};
+
+make_message_bus!(
+    bus::Toplevel [dead_letters = 8] => {
+        Values [2] => u32,
+        sensors::Sensors => {
+            Temperature [2] => i32,
+        },
+    },
+);
+
+#[test]
+fn lagging_subscribers_report_dropped_messages() {
+    let mut dead_letters = bus::DeadLetters::subscribe();
+    let mut values = bus::Values::subscribe().with_name("values");
+    let mut sensors = bus::Sensors::subscribe();
+
+    for i in 0..5 {
+        bus::Values::publish(i);
+        bus::sensors::Temperature::publish(i as i32);
+    }
+
+    assert_eq!(values.try_recv(), Some(3));
+    assert!(sensors.try_recv().is_some());
+
+    assert_eq!(
+        dead_letters.try_recv(),
+        Some(DroppedMessages {
+            topic: "Toplevel/Values",
+            count: 3,
+            subscriber: Some("values"),
+        })
+    );
+    assert_eq!(
+        dead_letters.try_recv(),
+        Some(DroppedMessages {
+            topic: "Toplevel/Sensors",
+            count: 3,
+            subscriber: None,
+        })
+    );
+    assert_eq!(dead_letters.try_recv(), None);
+
+    // The missed messages are still counted
+    assert_eq!(values.messages_lost(), 3);
+}
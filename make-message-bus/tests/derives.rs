@@ -0,0 +1,44 @@
+use make_message_bus::make_message_bus;
+use std::collections::HashSet;
+
+make_message_bus!(
+    #![derive(PartialEq)]
+
+    bus::Toplevel => {
+        Values => u32,
+        Floats => f32,
+        #[derive(Eq, Hash)]
+        #[allow(dead_code)]
+        ids::Ids => {
+            Id => u32,
+            #[derive(PartialOrd)]
+            names::Names => {
+                Name => String,
+            },
+        },
+    },
+);
+
+#[test]
+fn header_derives_apply_to_all_sub_topics() {
+    assert_eq!(bus::Toplevel::Values(1), bus::Toplevel::Values(1));
+    assert_ne!(bus::Toplevel::Floats(1.0), bus::Toplevel::Values(1));
+    assert_eq!(
+        bus::ids::Names::Name("a".into()),
+        bus::ids::Names::Name("a".into())
+    );
+}
+
+#[test]
+fn sub_topic_derives_are_inherited() {
+    let ids: HashSet<_> = [
+        bus::Ids::Id(1),
+        bus::Ids::Id(1),
+        bus::Ids::Names(bus::ids::Names::Name("a".into())),
+    ]
+    .into_iter()
+    .collect();
+
+    assert_eq!(ids.len(), 2);
+    assert!(bus::ids::Names::Name("a".into()) < bus::ids::Names::Name("b".into()));
+}
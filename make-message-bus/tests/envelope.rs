@@ -0,0 +1,73 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Readings [2, envelope] => u32,
+        Status [envelope, latest] => u32,
+        Commands [2, envelope, lossless] => u32,
+    },
+);
+
+#[tokio::test]
+async fn envelope_sequence_numbers_show_gaps() {
+    let mut sub = bus::Readings::subscribe();
+
+    bus::Readings::publish(1);
+    bus::Readings::publish_from("sensor", 2);
+
+    let first = sub.recv_envelope().await;
+    let second = sub.recv_envelope().await;
+
+    assert_eq!(first.payload, 1);
+    assert_eq!(first.source, None);
+    assert_eq!(second.payload, 2);
+    assert_eq!(second.source, Some("sensor"));
+    assert_eq!(second.seq, first.seq + 1);
+    assert!(second.published_at >= first.published_at);
+
+    for i in 3..=6 {
+        bus::Readings::publish(i);
+    }
+
+    // Messages 3 and 4 were lost, which is visible from the sequence numbers
+    let next = sub.recv_envelope().await;
+    assert_eq!(next.payload, 5);
+    assert_eq!(next.seq - second.seq - 1, 2);
+    assert_eq!(sub.messages_lost(), 2);
+    assert_eq!(sub.recv().await, 6);
+}
+
+#[tokio::test]
+async fn envelope_latest_and_parent_get_the_payload() {
+    let mut parent = bus::Toplevel::subscribe();
+
+    bus::Status::publish_from("status", 7);
+
+    assert_eq!(bus::Status::get(), Some(7));
+    assert!(matches!(parent.try_recv(), Some(bus::Toplevel::Status(7))));
+
+    let mut sub = bus::Status::subscribe();
+    let envelope = sub.try_recv_envelope().unwrap();
+
+    assert_eq!(envelope.payload, 7);
+    assert_eq!(envelope.source, Some("status"));
+}
+
+#[tokio::test]
+async fn envelope_lossless_returns_the_payload_when_full() {
+    let mut sub = bus::Commands::subscribe();
+
+    bus::Commands::publish(1).await;
+    bus::Commands::try_publish_from("commander", 2).unwrap();
+
+    assert_eq!(bus::Commands::try_publish(3).map_err(|full| full.0), Err(3));
+
+    assert_eq!(sub.try_recv(), Some(1));
+    let envelope = sub.try_recv_envelope().unwrap();
+
+    assert_eq!(envelope.payload, 2);
+    assert_eq!(envelope.source, Some("commander"));
+    assert_eq!(envelope.seq, 1);
+}
@@ -0,0 +1,55 @@
+use make_message_bus::make_message_bus;
+use std::{
+    future::Future,
+    pin::pin,
+    sync::Arc,
+    task::{Context, Poll, Wake},
+    thread::{self, Thread},
+};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values [4] => u32,
+    },
+);
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Minimal executor without any runtime, the bus must work without one.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        thread::park();
+    }
+}
+
+#[test]
+fn receive_without_runtime() {
+    let mut sub = bus::Values::subscribe();
+
+    let publisher = thread::spawn(|| {
+        for i in 0..3 {
+            bus::Values::publish(i);
+            thread::yield_now();
+        }
+    });
+
+    let received: Vec<u32> = (0..3).map(|_| block_on(sub.recv())).collect();
+
+    publisher.join().unwrap();
+    assert_eq!(received, [0, 1, 2]);
+    assert_eq!(sub.messages_lost(), 0);
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Log [16, history = 3] => u32,
+        Status [history = 3, latest] => u32,
+    },
+);
+
+#[tokio::test]
+async fn history_is_replayed() {
+    for i in 0..5 {
+        bus::Log::publish(i);
+    }
+
+    // Plain subscribers only get new values
+    let mut live = bus::Log::subscribe();
+    assert!(live.is_empty());
+
+    let mut replay = bus::Log::subscribe_with_history();
+    assert_eq!(replay.try_recv(), Some(2));
+    assert_eq!(replay.try_recv(), Some(3));
+    assert_eq!(replay.try_recv(), Some(4));
+    assert_eq!(replay.try_recv(), None);
+
+    bus::Log::publish(5);
+
+    assert_eq!(live.recv().await, 5);
+    assert_eq!(replay.recv().await, 5);
+}
+
+#[tokio::test]
+async fn history_and_latest() {
+    for i in 0..5 {
+        bus::Status::publish(i);
+    }
+
+    assert_eq!(bus::Status::get(), Some(4));
+
+    let mut latest = bus::Status::subscribe();
+    assert_eq!(latest.try_recv(), Some(4));
+    assert_eq!(latest.try_recv(), None);
+
+    let mut replay = bus::Status::subscribe_with_history();
+    assert_eq!(replay.try_recv(), Some(2));
+    assert_eq!(replay.try_recv(), Some(3));
+    assert_eq!(replay.try_recv(), Some(4));
+    assert_eq!(replay.try_recv(), None);
+}
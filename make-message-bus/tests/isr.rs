@@ -0,0 +1,135 @@
+// Publishing from interrupts needs a try-lock path, which the `tokio` and `async-broadcast`
+// channels don't have
+#![cfg(not(any(feature = "tokio", feature = "async-broadcast")))]
+
+#[cfg(feature = "std")]
+use make_message_bus::WouldBlock;
+use make_message_bus::{make_message_bus, PublishReport};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values => u32,
+        sensors::Sensors => {
+            Temperature [2] => i32,
+        },
+    },
+);
+
+#[test]
+fn publish_from_isr_forwards_to_parents() {
+    let mut sub = bus::sensors::Temperature::subscribe();
+    let mut parent = bus::Sensors::subscribe();
+
+    assert_eq!(
+        bus::sensors::Temperature::publish_from_isr(21),
+        PublishReport {
+            receivers: 1,
+            parent_receivers: 1
+        }
+    );
+    assert_eq!(
+        bus::sensors::Temperature::try_publish_nonblocking(22).unwrap(),
+        PublishReport {
+            receivers: 1,
+            parent_receivers: 1
+        }
+    );
+
+    assert_eq!(sub.try_recv(), Some(21));
+    assert_eq!(sub.try_recv(), Some(22));
+    assert!(matches!(
+        parent.try_recv(),
+        Some(bus::Sensors::Temperature(21))
+    ));
+    assert!(matches!(
+        parent.try_recv(),
+        Some(bus::Sensors::Temperature(22))
+    ));
+    assert_eq!(bus::sensors::Temperature::dropped_publishes(), 0);
+}
+
+#[test]
+fn publish_from_isr_without_subscribers() {
+    assert_eq!(
+        bus::Values::publish_from_isr(1),
+        PublishReport {
+            receivers: 0,
+            parent_receivers: 0
+        }
+    );
+    assert_eq!(bus::Values::dropped_publishes(), 0);
+}
+
+#[cfg(feature = "std")]
+make_message_bus!(
+    std_bus::Toplevel => {
+        Lossless [1, lossless] => u32,
+        Latest [latest] => u32,
+        Jobs [1, queue] => u32,
+        Woken => u32,
+    },
+);
+
+#[cfg(feature = "std")]
+#[test]
+fn full_lossless_topic_would_block() {
+    let mut sub = std_bus::Lossless::subscribe();
+
+    assert!(std_bus::Lossless::try_publish_nonblocking(1).is_ok());
+    assert!(matches!(
+        std_bus::Lossless::try_publish_nonblocking(2),
+        Err(WouldBlock(2))
+    ));
+
+    assert_eq!(std_bus::Lossless::publish_from_isr(3).receivers, 0);
+    assert_eq!(std_bus::Lossless::dropped_publishes(), 1);
+    assert_eq!(std_bus::Lossless::dropped_publishes(), 0);
+
+    assert_eq!(sub.try_recv(), Some(1));
+    assert_eq!(sub.try_recv(), None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn retained_topic_publishes_without_allocating_once_retained() {
+    // The first publish allocates the retained message storage
+    std_bus::Latest::publish(1);
+
+    assert!(std_bus::Latest::try_publish_nonblocking(2).is_ok());
+    assert_eq!(std_bus::Latest::get(), Some(2));
+    assert_eq!(std_bus::Latest::subscribe().try_recv(), Some(2));
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn queue_would_block_until_a_worker_is_taken() {
+    assert!(matches!(
+        std_bus::Jobs::try_publish_nonblocking(1),
+        Err(WouldBlock(1))
+    ));
+    assert_eq!(std_bus::Jobs::publish_from_isr(2).receivers, 0);
+    assert_eq!(std_bus::Jobs::dropped_publishes(), 1);
+
+    let mut worker = std_bus::Jobs::take_worker();
+
+    assert_eq!(std_bus::Jobs::publish_from_isr(3).receivers, 1);
+    assert_eq!(std_bus::Jobs::publish_from_isr(4).receivers, 0);
+    assert_eq!(std_bus::Jobs::dropped_publishes(), 1);
+    assert_eq!(std_bus::Jobs::messages_lost(), 0);
+    assert_eq!(worker.try_recv(), Some(3));
+    assert_eq!(worker.try_recv(), None);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn waiting_subscriber_is_woken_after_publishing_from_isr() {
+    let mut sub = std_bus::Woken::subscribe();
+    let waiting =
+        std::thread::spawn(move || sub.blocking_recv_timeout(std::time::Duration::from_secs(5)));
+
+    // Give the subscriber time to start waiting, it receives the message either way
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    assert_eq!(std_bus::Woken::publish_from_isr(1).receivers, 1);
+
+    assert_eq!(waiting.join().unwrap(), Ok(1));
+}
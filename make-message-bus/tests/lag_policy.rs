@@ -0,0 +1,86 @@
+use make_message_bus::{make_message_bus, LagPolicy, Lagged};
+use std::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Skipped [2] => u32,
+        Checked [2] => u32,
+        Panicking [2] => u32,
+        nested::Nested => {
+            Inner [2] => u32,
+        },
+    },
+);
+
+/// Poll a future which has what it waits for, the messages are published before receiving.
+fn ready<F: Future>(future: F) -> F::Output {
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => panic!("the future is not ready"),
+    }
+}
+
+#[test]
+fn skip_policy_skips_lagged_messages() {
+    let mut sub = bus::Skipped::subscribe_with(LagPolicy::Skip);
+
+    for i in 0..5 {
+        bus::Skipped::publish(i);
+    }
+
+    assert_eq!(ready(sub.recv_checked()), Ok(3));
+    assert_eq!(sub.messages_lost(), 3);
+}
+
+#[test]
+fn error_policy_reports_lagged_messages() {
+    let mut sub = bus::Checked::subscribe_with(LagPolicy::Error);
+
+    for i in 0..5 {
+        bus::Checked::publish(i);
+    }
+
+    assert_eq!(ready(sub.recv_checked()), Err(Lagged(3)));
+    assert_eq!(ready(sub.recv_checked()), Ok(3));
+    assert_eq!(ready(sub.recv_checked()), Ok(4));
+    assert_eq!(sub.messages_lost(), 3);
+
+    for i in 5..8 {
+        bus::Checked::publish(i);
+    }
+
+    // Receiving without checking skips as usual
+    assert_eq!(ready(sub.recv()), 6);
+    assert_eq!(sub.messages_lost(), 1);
+}
+
+#[test]
+fn error_policy_on_sub_topics() {
+    let mut sub = bus::Nested::subscribe_with(LagPolicy::Error);
+
+    for i in 0..5 {
+        bus::nested::Inner::publish(i);
+    }
+
+    assert_eq!(ready(sub.recv_checked()).unwrap_err(), Lagged(3));
+    assert!(matches!(
+        ready(sub.recv_checked()),
+        Ok(bus::Nested::Inner(3))
+    ));
+}
+
+#[test]
+#[should_panic(expected = "lagged behind and missed 1 messages")]
+fn panic_policy_panics_on_lag() {
+    let mut sub = bus::Panicking::subscribe_with(LagPolicy::Panic);
+
+    for i in 0..3 {
+        bus::Panicking::publish(i);
+    }
+
+    sub.try_recv();
+}
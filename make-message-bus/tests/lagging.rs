@@ -0,0 +1,24 @@
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values [2] => u32,
+    },
+);
+
+#[test]
+fn lagging_subscriber_skips_to_the_oldest_message() {
+    let mut sub = bus::Values::subscribe();
+
+    for i in 0..5 {
+        bus::Values::publish(i);
+    }
+
+    assert!(!sub.is_empty());
+    assert_eq!(sub.try_recv(), Some(3));
+    assert_eq!(sub.try_recv(), Some(4));
+    assert_eq!(sub.try_recv(), None);
+    assert!(sub.is_empty());
+    assert_eq!(sub.messages_lost(), 3);
+    assert_eq!(sub.messages_lost(), 0);
+}
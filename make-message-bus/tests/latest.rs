@@ -0,0 +1,50 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Battery [latest] => u8,
+        Connected [4, latest] => bool,
+    },
+);
+
+#[tokio::test]
+async fn latest_value_is_retained() {
+    assert_eq!(bus::Battery::get(), None);
+
+    let mut early = bus::Battery::subscribe();
+    assert!(early.is_empty());
+
+    bus::Battery::publish(90);
+    bus::Battery::publish(80);
+
+    assert_eq!(bus::Battery::get(), Some(80));
+
+    // A late subscriber gets the latest value first
+    let mut late = bus::Battery::subscribe();
+    assert_eq!(late.try_recv(), Some(80));
+    assert_eq!(late.try_recv(), None);
+
+    // An early subscriber gets the values as they were published
+    assert_eq!(early.recv().await, 80);
+    assert_eq!(early.messages_lost(), 1);
+
+    bus::Battery::publish(70);
+
+    assert_eq!(late.recv().await, 70);
+    assert_eq!(early.recv().await, 70);
+}
+
+#[tokio::test]
+async fn latest_value_is_forwarded_to_parent() {
+    let mut parent = bus::Toplevel::subscribe();
+
+    bus::Connected::publish(true);
+
+    assert!(matches!(
+        parent.recv().await,
+        bus::Toplevel::Connected(true)
+    ));
+    assert!(bus::Connected::subscribe().recv().await);
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::{make_message_bus, Full};
+use std::time::Duration;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Commands [2, lossless] => u32,
+        Writes [2, lossless] => u32,
+        Jobs [2, lossless, queue] => u32,
+    },
+);
+
+#[tokio::test]
+async fn lossless_publish_waits_for_subscribers() {
+    let mut parent = bus::Toplevel::subscribe();
+    let mut sub = bus::Commands::subscribe();
+
+    bus::Commands::try_publish(1).unwrap();
+    bus::Commands::publish(2).await;
+
+    // The subscriber has not received anything, so there is no room
+    assert!(matches!(bus::Commands::try_publish(3), Err(Full(3))));
+
+    let publisher = tokio::spawn(async {
+        bus::Commands::publish(3).await;
+        bus::Commands::publish(4).await;
+    });
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(!publisher.is_finished());
+
+    for i in 1..=4 {
+        assert_eq!(sub.recv().await, i);
+    }
+
+    publisher.await.unwrap();
+    assert_eq!(sub.messages_lost(), 0);
+
+    // Rejected messages are not forwarded to the parent
+    for i in 1..=4 {
+        assert!(matches!(parent.try_recv(), Some(bus::Toplevel::Commands(v)) if v == i));
+    }
+    assert!(parent.try_recv().is_none());
+}
+
+#[tokio::test]
+async fn dropping_a_subscriber_frees_room() {
+    let sub = bus::Writes::subscribe();
+
+    for i in 0..2 {
+        bus::Writes::try_publish(i).unwrap();
+    }
+
+    let publisher = tokio::spawn(bus::Writes::publish(2));
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(!publisher.is_finished());
+
+    drop(sub);
+    publisher.await.unwrap();
+}
+
+#[tokio::test]
+async fn lossless_queue() {
+    let mut worker = bus::Jobs::take_worker();
+
+    bus::Jobs::publish(1).await;
+    bus::Jobs::try_publish(2).unwrap();
+    assert!(matches!(bus::Jobs::try_publish(3), Err(Full(3))));
+
+    let publisher = tokio::spawn(bus::Jobs::publish(3));
+
+    for i in 1..=3 {
+        assert_eq!(worker.recv().await, i);
+    }
+
+    publisher.await.unwrap();
+    assert_eq!(bus::Jobs::messages_lost(), 0);
+}
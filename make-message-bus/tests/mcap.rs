@@ -0,0 +1,146 @@
+#![cfg(feature = "mcap")]
+
+use make_message_bus::{make_message_bus, McapReader, McapWriter, ReplayError, ReplaySpeed};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values [8] => u32,
+        sub8::SubTopic8 => {
+            sub12::SubTopic12 => {
+                Topic13 [8] => u8,
+            },
+        },
+    },
+);
+
+const MAGIC: &[u8] = b"\x89MCAP0\r\n";
+
+/// Checks if `needle` is in `haystack`.
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+/// A record with its opcode and length.
+fn record(opcode: u8, fields: &[&[u8]]) -> Vec<u8> {
+    let fields = fields.concat();
+    let mut record = vec![opcode];
+    record.extend_from_slice(&(fields.len() as u64).to_le_bytes());
+    record.extend_from_slice(&fields);
+    record
+}
+
+/// A length-prefixed string.
+fn string(s: &str) -> Vec<u8> {
+    [&(s.len() as u32).to_le_bytes(), s.as_bytes()].concat()
+}
+
+/// A file with a channel for `Toplevel/Values` and a chunk with the message `7` on it.
+fn chunked_file(compression: &str) -> Vec<u8> {
+    let channel = record(
+        0x04,
+        &[
+            &1u16.to_le_bytes(),
+            &0u16.to_le_bytes(),
+            &string("/Toplevel/Values"),
+            &string("json"),
+            &0u32.to_le_bytes(),
+        ],
+    );
+    let message = record(
+        0x05,
+        &[&1u16.to_le_bytes(), &0u32.to_le_bytes(), &[0; 16], b"7"],
+    );
+    let chunk = record(
+        0x06,
+        &[
+            &[0; 28],
+            &string(compression),
+            &(message.len() as u64).to_le_bytes(),
+            &message,
+        ],
+    );
+
+    [MAGIC, &channel, &chunk, &record(0x0f, &[&[0; 4]])].concat()
+}
+
+#[tokio::test]
+async fn record_and_replay_mcap() {
+    let mut sub = bus::Toplevel::subscribe();
+    let mut file = Vec::new();
+
+    bus::Values::publish(1);
+    bus::sub8::sub12::Topic13::publish(2);
+    bus::sub8::sub12::Topic13::publish(3);
+
+    {
+        let mut writer = McapWriter::new(&mut file).unwrap();
+
+        while let Some(message) = sub.try_recv() {
+            writer.write(&message).unwrap();
+        }
+    }
+
+    assert!(file.starts_with(MAGIC));
+    assert!(file.ends_with(MAGIC));
+    assert!(contains(&file, b"/Toplevel/Values"));
+    assert!(contains(&file, b"/Toplevel/SubTopic8/SubTopic12/Topic13"));
+    assert!(!contains(&file, b"jsonschema"));
+
+    let mut reader = McapReader::new(file.as_slice()).unwrap();
+    let record = reader.read_record().unwrap().unwrap();
+    assert_eq!(record.path, "Toplevel/Values");
+    assert_eq!(record.payload, b"1");
+
+    let mut topic13 = bus::sub8::sub12::Topic13::subscribe();
+
+    assert_eq!(
+        reader
+            .replay::<bus::Toplevel>(ReplaySpeed::AsFastAsPossible)
+            .await
+            .unwrap(),
+        2
+    );
+    assert_eq!(topic13.try_recv(), Some(2));
+    assert_eq!(topic13.try_recv(), Some(3));
+    assert!(reader.read_record().unwrap().is_none());
+}
+
+#[test]
+fn uncompressed_chunks_are_read() {
+    let file = chunked_file("");
+    let mut reader = McapReader::new(file.as_slice()).unwrap();
+
+    let record = reader.read_record().unwrap().unwrap();
+    assert_eq!(record.path, "Toplevel/Values");
+    assert_eq!(record.payload, b"7");
+    assert!(reader.read_record().unwrap().is_none());
+}
+
+#[test]
+fn compressed_chunks_are_unsupported() {
+    assert!(matches!(
+        McapReader::new(chunked_file("zstd").as_slice())
+            .unwrap()
+            .read_record(),
+        Err(ReplayError::Unsupported(_))
+    ));
+    assert!(matches!(
+        McapReader::new(b"MBUSLOG1".as_slice()),
+        Err(ReplayError::NotALog)
+    ));
+}
+
+#[test]
+fn corrupt_record_lengths_are_not_allocated() {
+    let mut file = MAGIC.to_vec();
+    file.push(0x05);
+    file.extend_from_slice(&u64::MAX.to_le_bytes());
+    file.extend_from_slice(b"short");
+
+    assert!(matches!(
+        McapReader::new(file.as_slice()).unwrap().read_record(),
+        Err(ReplayError::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+}
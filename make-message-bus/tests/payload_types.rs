@@ -0,0 +1,68 @@
+// Not all of the payloads can be serialized
+#![cfg(all(feature = "std", not(any(feature = "serde", feature = "record"))))]
+
+use make_message_bus::make_message_bus;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Foo(u8);
+
+make_message_bus!(
+    bus::Toplevel => {
+        Tuple => (u8, u16),
+        Array [2] => [u8; 16],
+        Shared => std::sync::Arc<str>,
+        Optional => Option<crate::Foo>,
+        Static => &'static str,
+        Callback => fn(u8) -> u8,
+        Fallible => u8 -> Result<u8, Box<dyn std::error::Error + Send + Sync>>,
+    },
+);
+
+#[test]
+fn tuples_arrays_and_references() {
+    let mut sub = bus::Toplevel::subscribe();
+
+    bus::Tuple::publish((1, 2));
+    bus::Array::publish([3; 16]);
+    bus::Shared::publish("shared".into());
+    bus::Optional::publish(Some(Foo(4)));
+    bus::Static::publish("static");
+    bus::Callback::publish(|x| x + 1);
+
+    assert!(matches!(sub.try_recv(), Some(bus::Toplevel::Tuple((1, 2)))));
+    assert!(matches!(
+        sub.try_recv(),
+        Some(bus::Toplevel::Array([3, ..]))
+    ));
+    assert!(matches!(sub.try_recv(), Some(bus::Toplevel::Shared(s)) if &*s == "shared"));
+    assert!(matches!(
+        sub.try_recv(),
+        Some(bus::Toplevel::Optional(Some(Foo(4))))
+    ));
+    assert!(matches!(
+        sub.try_recv(),
+        Some(bus::Toplevel::Static("static"))
+    ));
+    assert!(matches!(sub.try_recv(), Some(bus::Toplevel::Callback(f)) if f(1) == 2));
+}
+
+#[tokio::test]
+async fn boxed_errors_as_responses() {
+    tokio::spawn(bus::Fallible::take_responder().serve(|request| {
+        if request > 0 {
+            Ok(request)
+        } else {
+            Err("zero".into())
+        }
+    }));
+
+    assert_eq!(bus::Fallible::request(1).await.unwrap().unwrap(), 1);
+    assert_eq!(
+        bus::Fallible::request(0)
+            .await
+            .unwrap()
+            .unwrap_err()
+            .to_string(),
+        "zero"
+    );
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "prometheus")]
+
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values => u32,
+        sensors::Sensors => {
+            Temperature => i32,
+        },
+    },
+);
+
+#[test]
+fn render_metrics_labels_topics_by_path() {
+    let _sub = bus::sensors::Temperature::subscribe();
+    bus::sensors::Temperature::publish(21);
+
+    let metrics = bus::Toplevel::render_metrics();
+    let lines: Vec<_> = metrics.lines().collect();
+
+    assert!(lines.contains(&"# TYPE message_bus_published_total counter"));
+    assert!(lines.contains(&"message_bus_published_total{topic=\"Toplevel/Values\"} 0"));
+    assert!(
+        lines.contains(&"message_bus_published_total{topic=\"Toplevel/Sensors/Temperature\"} 1")
+    );
+    assert!(lines.contains(&"message_bus_subscribers{topic=\"Toplevel/Sensors/Temperature\"} 1"));
+    assert!(lines.contains(&"message_bus_messages_lost_total{topic=\"Toplevel/Sensors\"} 0"));
+
+    // Only topics which have been published to, directly or below them, have a last publish time
+    let timestamps: Vec<_> = lines
+        .iter()
+        .filter(|line| line.starts_with("message_bus_last_published_timestamp_seconds{"))
+        .collect();
+
+    assert_eq!(timestamps.len(), 3);
+    assert!(!timestamps
+        .iter()
+        .any(|line| line.contains("Toplevel/Values")));
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::{make_message_bus, PublishReport};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Topic1 => u8,
+        t1::SubTopic2 => {
+            Topic3 => u8,
+            Jobs [queue] => u8,
+            Commands [lossless] => u8,
+        },
+    },
+);
+
+#[tokio::test]
+async fn publish_reports_receivers() {
+    assert!(!bus::t1::Topic3::has_subscribers());
+    assert_eq!(bus::t1::Topic3::publish(1), PublishReport::default());
+    assert!(!bus::t1::Topic3::publish(1).is_received());
+
+    let _sub1 = bus::t1::Topic3::subscribe();
+    let _sub2 = bus::t1::Topic3::subscribe();
+    let _sub_sub_topic = bus::SubTopic2::subscribe();
+    let _sub_toplevel = bus::Toplevel::subscribe();
+
+    assert!(bus::t1::Topic3::has_subscribers());
+    assert_eq!(bus::t1::Topic3::receiver_count(), 2);
+    assert_eq!(bus::SubTopic2::receiver_count(), 1);
+    assert!(bus::Toplevel::has_subscribers());
+
+    let report = bus::t1::Topic3::publish(1);
+    assert_eq!(
+        report,
+        PublishReport {
+            receivers: 2,
+            parent_receivers: 2,
+        }
+    );
+    assert!(report.is_received());
+
+    // Only the parents
+    assert_eq!(
+        bus::Topic1::publish(1),
+        PublishReport {
+            receivers: 0,
+            parent_receivers: 1,
+        }
+    );
+
+    let _worker = bus::t1::Jobs::take_worker();
+    assert_eq!(bus::t1::Jobs::receiver_count(), 1);
+    assert_eq!(bus::t1::Jobs::publish(1).receivers, 1);
+
+    let mut sub = bus::t1::Commands::subscribe();
+    assert_eq!(bus::t1::Commands::publish(1).await.receivers, 1);
+    assert_eq!(sub.recv().await, 1);
+    assert_eq!(
+        bus::t1::Commands::try_publish(1).unwrap().parent_receivers,
+        2
+    );
+}
@@ -0,0 +1,37 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Jobs [4, queue] => u32,
+    },
+);
+
+#[tokio::test]
+async fn each_job_is_received_once() {
+    let mut observer = bus::Toplevel::subscribe();
+    let mut worker1 = bus::Jobs::take_worker();
+    let mut worker2 = bus::Jobs::take_worker();
+
+    bus::Jobs::publish(1);
+    bus::Jobs::publish(2);
+
+    assert_eq!(worker1.recv().await, 1);
+    assert_eq!(worker2.recv().await, 2);
+    assert!(worker1.is_empty());
+    assert_eq!(worker2.try_recv(), None);
+
+    // Jobs are mirrored to the parent topic for observers
+    assert!(matches!(observer.recv().await, bus::Toplevel::Jobs(1)));
+    assert!(matches!(observer.recv().await, bus::Toplevel::Jobs(2)));
+
+    // A full queue drops the oldest job
+    for i in 0..6 {
+        bus::Jobs::publish(i);
+    }
+
+    assert_eq!(bus::Jobs::messages_lost(), 2);
+    assert_eq!(worker1.recv().await, 2);
+    assert_eq!(worker2.recv().await, 3);
+}
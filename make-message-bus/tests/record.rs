@@ -0,0 +1,158 @@
+#![cfg(feature = "record")]
+
+use make_message_bus::{
+    make_message_bus, Recordable, Recorder, ReplayError, ReplaySpeed, Replayer,
+};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    x: f32,
+    y: f32,
+}
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values [8] => u32,
+        nav::Nav => {
+            Position [8] => crate::Position,
+            Name [8] => String,
+        },
+        // Sub-topic enums without variants
+        empty::Empty => {},
+        rpc::RpcOnly => {
+            Echo => u32 -> u32,
+        },
+    },
+);
+
+// Separate bus, so the tests don't record each other's messages
+make_message_bus!(
+    timed::Timed => {
+        Lossless [8, lossless] => u32,
+    },
+);
+
+/// Record the messages published on `T` by each of `publishes`, and return the log.
+fn record<T: Recordable + Clone>(
+    mut sub: make_message_bus::Subscriber<T>,
+    publishes: &[&dyn Fn()],
+) -> Vec<u8> {
+    let mut recorder = Recorder::new(Vec::new()).unwrap();
+
+    for publish in publishes {
+        publish();
+
+        while let Some(message) = sub.try_recv() {
+            recorder.write(&message).unwrap();
+        }
+    }
+
+    recorder.into_inner()
+}
+
+#[tokio::test]
+async fn record_and_replay() {
+    let log = record(
+        bus::Toplevel::subscribe(),
+        &[&|| {
+            bus::Values::publish(1);
+            bus::nav::Position::publish(Position { x: 1.0, y: 2.0 });
+            bus::nav::Name::publish("rover".into());
+        }],
+    );
+
+    let mut replayer = Replayer::new(log.as_slice()).unwrap();
+    let record = replayer.read_record().unwrap().unwrap();
+    assert_eq!(record.path, "Toplevel/Values");
+    assert_eq!(record.payload, b"1");
+
+    let mut values = bus::Values::subscribe();
+    let mut nav = bus::Nav::subscribe();
+
+    assert_eq!(
+        replayer
+            .replay::<bus::Toplevel>(ReplaySpeed::AsFastAsPossible)
+            .await
+            .unwrap(),
+        2
+    );
+
+    assert_eq!(values.try_recv(), None);
+    assert!(matches!(
+        nav.try_recv(),
+        Some(bus::Nav::Position(Position { x: 1.0, y: 2.0 }))
+    ));
+    assert!(matches!(nav.try_recv(), Some(bus::Nav::Name(name)) if name == "rover"));
+    assert!(replayer.read_record().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn replay_at_original_speed() {
+    let log = record(
+        timed::Timed::subscribe(),
+        &[
+            &|| {
+                timed::Lossless::try_publish(1).unwrap();
+            },
+            &|| {
+                std::thread::sleep(Duration::from_millis(50));
+                timed::Lossless::try_publish(2).unwrap();
+            },
+        ],
+    );
+
+    let mut sub = timed::Lossless::subscribe();
+    let start = Instant::now();
+
+    let count = Replayer::new(log.as_slice())
+        .unwrap()
+        .replay::<timed::Timed>(ReplaySpeed::Original)
+        .await
+        .unwrap();
+
+    assert_eq!(count, 2);
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(sub.try_recv(), Some(1));
+    assert_eq!(sub.try_recv(), Some(2));
+}
+
+#[test]
+fn sub_topics_are_recordable() {
+    let message = bus::Toplevel::Nav(bus::Nav::Name("rover".into()));
+
+    assert_eq!(message.path(), "Toplevel/Nav/Name");
+    assert_eq!(message.encode_payload().unwrap(), b"\"rover\"");
+    assert!(matches!(
+        bus::Nav::decode("Toplevel/Nav/Name", b"\"rover\""),
+        Ok(bus::Nav::Name(name)) if name == "rover"
+    ));
+}
+
+#[test]
+fn replay_errors() {
+    assert!(matches!(
+        Replayer::new(b"not a log".as_slice()),
+        Err(ReplayError::NotALog)
+    ));
+    assert!(matches!(
+        bus::Toplevel::decode("Toplevel/Missing", b"1"),
+        Err(ReplayError::UnknownTopic(path)) if path == "Toplevel/Missing"
+    ));
+    assert!(matches!(
+        bus::Toplevel::decode("Toplevel/Values", b"\"one\""),
+        Err(ReplayError::Payload(_))
+    ));
+
+    // A corrupt length is not trusted, the log ends long before it
+    let mut log = b"MBUSLOG1".to_vec();
+    log.extend_from_slice(&0u64.to_le_bytes());
+    log.extend_from_slice(&u32::MAX.to_le_bytes());
+    log.extend_from_slice(b"short");
+
+    assert!(matches!(
+        Replayer::new(log.as_slice()).unwrap().read_record(),
+        Err(ReplayError::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+}
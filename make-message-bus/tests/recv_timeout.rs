@@ -0,0 +1,87 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::{make_message_bus, Timeout};
+use std::time::{Duration, Instant};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values => u32,
+        Deadline => u32,
+        Burst [4] => u32,
+        Short => u32,
+    },
+);
+
+#[tokio::test]
+async fn recv_timeout_gives_up() {
+    let mut sub = bus::Values::subscribe();
+
+    assert_eq!(
+        sub.recv_timeout(Duration::from_millis(20)).await,
+        Err(Timeout)
+    );
+
+    bus::Values::publish(1);
+
+    assert_eq!(sub.recv_timeout(Duration::from_secs(5)).await, Ok(1));
+}
+
+#[tokio::test]
+async fn recv_deadline_gives_up() {
+    let mut sub = bus::Deadline::subscribe();
+
+    assert_eq!(sub.recv_deadline(Instant::now()).await, Err(Timeout));
+
+    tokio::spawn(async {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        bus::Deadline::publish(2);
+    });
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    assert_eq!(sub.recv_deadline(deadline).await, Ok(2));
+}
+
+#[tokio::test]
+async fn recv_many_drains_bursts() {
+    let mut sub = bus::Burst::subscribe();
+    let mut buffer = Vec::new();
+
+    for i in 0..6 {
+        bus::Burst::publish(i);
+    }
+
+    assert_eq!(sub.recv_many(&mut buffer, 3).await, 3);
+    assert_eq!(buffer, [2, 3, 4]);
+    assert_eq!(sub.messages_lost(), 2);
+
+    assert_eq!(sub.recv_many(&mut buffer, 0).await, 0);
+    assert_eq!(sub.recv_many(&mut buffer, 10).await, 1);
+    assert_eq!(buffer, [2, 3, 4, 5]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn many_short_timeouts() {
+    let tasks: Vec<_> = (0..1000)
+        .map(|i| {
+            tokio::spawn(async move {
+                let mut sub = bus::Short::subscribe();
+
+                if i % 2 == 0 {
+                    sub.recv_timeout(Duration::from_millis(1)).await
+                } else {
+                    // Dropped long before its deadline
+                    tokio::time::timeout(
+                        Duration::from_millis(1),
+                        sub.recv_timeout(Duration::from_secs(60)),
+                    )
+                    .await
+                    .unwrap_or(Err(Timeout))
+                }
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), Err(Timeout));
+    }
+}
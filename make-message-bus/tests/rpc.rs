@@ -0,0 +1,68 @@
+#![cfg(feature = "std")]
+
+use make_message_bus::{make_message_bus, RpcError};
+use std::time::Duration;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Double => u32 -> u64,
+        Unserved => u32 -> u32,
+        Ignored => u32 -> u32,
+        Abandoned => u32 -> u32,
+        Data => u32,
+    },
+);
+
+#[tokio::test]
+async fn request_is_served() {
+    tokio::spawn(bus::Double::serve(|req| req as u64 * 2));
+
+    // Wait for the server to be registered
+    tokio::task::yield_now().await;
+
+    assert_eq!(bus::Double::request(21).await, Ok(42));
+    assert_eq!(
+        bus::Double::request_timeout(2, Duration::from_secs(1)).await,
+        Ok(4)
+    );
+}
+
+#[tokio::test]
+async fn request_without_server() {
+    assert_eq!(bus::Unserved::request(1).await, Err(RpcError::NoServer));
+}
+
+#[tokio::test]
+async fn request_timeout_and_dropped_request() {
+    let mut responder = bus::Ignored::take_responder();
+
+    assert_eq!(
+        bus::Ignored::request_timeout(1, Duration::from_millis(10)).await,
+        Err(RpcError::Timeout)
+    );
+
+    let server = tokio::spawn(async move {
+        let request = responder.recv().await;
+        assert_eq!(*request.payload(), 2);
+    });
+
+    assert_eq!(bus::Ignored::request(2).await, Err(RpcError::NoResponse));
+    server.await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn request_racing_last_responder_drop() {
+    for _ in 0..1000 {
+        let responder = bus::Abandoned::take_responder();
+        let dropper = std::thread::spawn(move || drop(responder));
+
+        let response = bus::Abandoned::request_timeout(1, Duration::from_secs(1)).await;
+
+        // Never left waiting for a response which can't come
+        assert!(matches!(
+            response,
+            Err(RpcError::NoServer | RpcError::NoResponse)
+        ));
+        dropper.join().unwrap();
+    }
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "serde")]
+
+use make_message_bus::make_message_bus;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    x: f32,
+    y: f32,
+}
+
+make_message_bus!(
+    bus::Toplevel [subscribers = 2] => {
+        Values [2] => u32,
+        nav::Nav [subscribers = 2] => {
+            Position [2] => crate::Position,
+        },
+    },
+);
+
+#[test]
+fn enums_are_externally_tagged_by_topic_name() {
+    let values = bus::Toplevel::Values(1);
+    let position = bus::Toplevel::Nav(bus::Nav::Position(Position { x: 1.0, y: 2.0 }));
+
+    assert_eq!(serde_json::to_string(&values).unwrap(), r#"{"Values":1}"#);
+    assert_eq!(
+        serde_json::to_string(&position).unwrap(),
+        r#"{"Nav":{"Position":{"x":1.0,"y":2.0}}}"#
+    );
+
+    assert!(matches!(
+        serde_json::from_str(r#"{"Nav":{"Position":{"x":1.0,"y":2.0}}}"#),
+        Ok(bus::Toplevel::Nav(bus::Nav::Position(Position {
+            x: 1.0,
+            y: 2.0
+        })))
+    ));
+    assert!(serde_json::from_str::<bus::Toplevel>(r#"{"Missing":1}"#).is_err());
+}
+
+#[test]
+fn received_messages_round_trip() {
+    let mut sub = bus::Toplevel::subscribe();
+    bus::nav::Position::publish(Position { x: 3.0, y: 4.0 });
+
+    let json = serde_json::to_string(&sub.try_recv().unwrap()).unwrap();
+    let message: bus::Toplevel = serde_json::from_str(&json).unwrap();
+
+    assert!(matches!(
+        message,
+        bus::Toplevel::Nav(bus::Nav::Position(Position { x: 3.0, y: 4.0 }))
+    ));
+}
@@ -0,0 +1,125 @@
+// The executor only drives the `critical-section` backend, which is used without `std`
+#![cfg(all(feature = "critical-section", not(feature = "std")))]
+//! Subscribers running as tasks on a simulated single-threaded executor, the way they run on
+//! embassy or as RTIC async software tasks, with an "interrupt" publishing whenever the executor
+//! has nothing to do.
+
+use make_message_bus::make_message_bus;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    future::Future,
+    pin::Pin,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    task::{Context, Wake, Waker},
+};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Sensor [4] => u32,
+        control::Control => {
+            Setpoint [4] => u32,
+        },
+    },
+);
+
+type Task = Pin<Box<dyn Future<Output = ()>>>;
+
+struct TaskWaker {
+    id: usize,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.ready.lock().unwrap().push_back(self.id);
+    }
+}
+
+#[derive(Default)]
+struct Executor {
+    tasks: Vec<Option<Task>>,
+    polls: Vec<usize>,
+    ready: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl Executor {
+    fn spawn(&mut self, task: impl Future<Output = ()> + 'static) {
+        self.ready.lock().unwrap().push_back(self.tasks.len());
+        self.tasks.push(Some(Box::pin(task)));
+        self.polls.push(0);
+    }
+
+    /// Run until all tasks are done, calling `interrupt` whenever no task is ready, like an
+    /// interrupt waking the executor from sleep.
+    fn run(&mut self, mut interrupt: impl FnMut()) {
+        while self.tasks.iter().any(Option::is_some) {
+            let next = self.ready.lock().unwrap().pop_front();
+
+            let Some(id) = next else {
+                interrupt();
+                continue;
+            };
+
+            let Some(task) = &mut self.tasks[id] else {
+                continue;
+            };
+
+            let waker = Waker::from(Arc::new(TaskWaker {
+                id,
+                ready: self.ready.clone(),
+            }));
+
+            self.polls[id] += 1;
+
+            if task
+                .as_mut()
+                .poll(&mut Context::from_waker(&waker))
+                .is_ready()
+            {
+                self.tasks[id] = None;
+            }
+        }
+    }
+}
+
+#[test]
+fn tasks_are_woken_by_publishing_from_an_interrupt() {
+    let sensor = Rc::new(RefCell::new(Vec::new()));
+    let control = Rc::new(RefCell::new(Vec::new()));
+    let mut executor = Executor::default();
+
+    let mut sensor_sub = bus::Sensor::subscribe();
+    let received = sensor.clone();
+    executor.spawn(async move {
+        for _ in 0..3 {
+            let value = sensor_sub.recv().await;
+            received.borrow_mut().push(value);
+        }
+    });
+
+    let mut control_sub = bus::Control::subscribe();
+    let received = control.clone();
+    executor.spawn(async move {
+        for _ in 0..3 {
+            let bus::Control::Setpoint(setpoint) = control_sub.recv().await;
+            received.borrow_mut().push(setpoint);
+        }
+    });
+
+    let mut interrupts = 0;
+    executor.run(|| {
+        assert!(interrupts < 3, "the tasks were not woken");
+        interrupts += 1;
+
+        bus::Sensor::publish(interrupts);
+        bus::control::Setpoint::publish(interrupts * 10);
+    });
+
+    assert_eq!(*sensor.borrow(), [1, 2, 3]);
+    assert_eq!(*control.borrow(), [10, 20, 30]);
+
+    // Polled once when spawned and once per message, tasks are only polled when woken
+    assert_eq!(executor.polls, [4, 4]);
+}
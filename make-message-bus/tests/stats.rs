@@ -0,0 +1,66 @@
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values [2] => u32,
+        sub8::SubTopic8 => {
+            sub12::SubTopic12 => {
+                Topic13 [4] => u8,
+            },
+        },
+    },
+);
+
+#[test]
+fn stats_count_publishes_subscribers_and_losses() {
+    let mut sub = bus::Values::subscribe();
+
+    for i in 0..5 {
+        bus::Values::publish(i);
+    }
+
+    assert_eq!(sub.try_recv(), Some(3));
+
+    let stats = bus::Values::stats();
+    assert_eq!(stats.published, 5);
+    assert_eq!(stats.subscribers, 1);
+    assert_eq!(stats.messages_lost, 3);
+    #[cfg(feature = "std")]
+    assert!(stats.last_published.is_some());
+}
+
+#[test]
+fn bus_stats_are_keyed_by_path() {
+    let _sub = bus::sub8::sub12::Topic13::subscribe();
+    bus::sub8::sub12::Topic13::publish(1);
+
+    let stats = bus::stats();
+    let paths: Vec<_> = stats.iter().map(|(path, _)| *path).collect();
+
+    assert_eq!(
+        paths,
+        [
+            "Toplevel",
+            "Toplevel/Values",
+            "Toplevel/SubTopic8",
+            "Toplevel/SubTopic8/SubTopic12",
+            "Toplevel/SubTopic8/SubTopic12/Topic13",
+        ]
+    );
+
+    let topic13 = stats.get("Toplevel/SubTopic8/SubTopic12/Topic13").unwrap();
+    assert_eq!(topic13.published, 1);
+    assert_eq!(topic13.subscribers, 1);
+    assert_eq!(topic13.messages_lost, 0);
+
+    // Parent sub-topics without subscribers are not published to, but count the publish
+    assert_eq!(stats.get("Toplevel/SubTopic8").unwrap().published, 1);
+    assert_eq!(
+        stats
+            .get("Toplevel/SubTopic8/SubTopic12")
+            .unwrap()
+            .published,
+        1
+    );
+    assert!(stats.get("Toplevel/Missing").is_none());
+}
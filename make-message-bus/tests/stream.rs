@@ -0,0 +1,47 @@
+#![cfg(all(feature = "std", feature = "stream"))]
+
+use core::marker::PhantomPinned;
+use make_message_bus::{make_message_bus, SubscriberStream};
+use tokio_stream::StreamExt;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Small [2] => u32,
+        Large [10] => u32,
+    },
+);
+
+#[tokio::test]
+async fn stream_receives_in_order() {
+    let mut stream = bus::Large::subscribe().into_stream();
+
+    for i in 0..5 {
+        bus::Large::publish(i);
+    }
+
+    let received: Vec<u32> = (&mut stream).take(5).collect().await;
+
+    assert_eq!(received, [0, 1, 2, 3, 4]);
+    assert_eq!(stream.messages_lost(), 0);
+}
+
+#[tokio::test]
+async fn stream_counts_lagged_messages() {
+    let mut stream = bus::Small::subscribe().into_stream();
+
+    for i in 0..5 {
+        bus::Small::publish(i);
+    }
+
+    assert_eq!(stream.next().await, Some(3));
+    assert_eq!(stream.next().await, Some(4));
+    assert_eq!(stream.messages_lost(), 3);
+    assert_eq!(stream.messages_lost(), 0);
+}
+
+#[test]
+fn stream_of_payloads_which_are_not_unpin_is_unpin() {
+    fn assert_unpin<T: Unpin>() {}
+
+    assert_unpin::<SubscriberStream<PhantomPinned>>();
+}
@@ -0,0 +1,44 @@
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        Bounded [2, subscribers = 2] => u32,
+        Full [subscribers = 1] => u32,
+        limited::Limited [subscribers = 1] => {
+            Inner => u32,
+        },
+    },
+);
+
+#[test]
+fn subscriber_slots_are_freed_on_drop() {
+    let mut sub1 = bus::Bounded::subscribe();
+    let sub2 = bus::Bounded::subscribe();
+
+    assert_eq!(bus::Bounded::publish(1).receivers, 2);
+    assert_eq!(bus::Bounded::receiver_count(), 2);
+
+    drop(sub2);
+    let mut sub3 = bus::Bounded::subscribe();
+
+    bus::Bounded::publish(2);
+
+    assert_eq!(sub1.try_recv(), Some(1));
+    assert_eq!(sub1.try_recv(), Some(2));
+    assert_eq!(sub3.try_recv(), Some(2));
+    assert_eq!(sub3.try_recv(), None);
+}
+
+#[test]
+#[should_panic(expected = "maximum number of subscribers")]
+fn subscribing_past_the_maximum_panics() {
+    let _sub1 = bus::Full::subscribe();
+    let _sub2 = bus::Full::subscribe();
+}
+
+#[test]
+#[should_panic(expected = "maximum number of subscribers")]
+fn sub_topic_subscribers_are_bounded() {
+    let _sub1 = bus::Limited::subscribe();
+    let _sub2 = bus::Limited::subscribe();
+}
@@ -0,0 +1,168 @@
+#![cfg(all(feature = "tracing", feature = "std"))]
+
+use make_message_bus::make_message_bus;
+use std::{
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Metadata,
+};
+
+make_message_bus!(
+    bus::Toplevel => {
+        Values => u32,
+        Lagging [2] => u32,
+        Traced [envelope] => u32,
+    },
+);
+
+// Publishing from interrupts needs a try-lock path, which the `tokio` and `async-broadcast`
+// channels don't have
+#[cfg(not(any(feature = "tokio", feature = "async-broadcast")))]
+make_message_bus!(
+    isr_bus::Toplevel => {
+        FromIsr [2, envelope] => u32,
+    },
+);
+
+/// Collects the level and fields of all events, and tracks the entered span.
+#[derive(Clone, Default)]
+struct Collector {
+    events: Arc<Mutex<Vec<(Level, String)>>>,
+    spans: Arc<Mutex<Vec<&'static Metadata<'static>>>>,
+    entered: Arc<Mutex<Option<span::Id>>>,
+    next_id: Arc<AtomicU64>,
+}
+
+struct Fields(String);
+
+impl Visit for Fields {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0 += &format!("{}={:?} ", field.name(), value);
+    }
+}
+
+impl tracing::Subscriber for Collector {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &span::Attributes<'_>) -> span::Id {
+        self.spans.lock().unwrap().push(attributes.metadata());
+        span::Id::from_u64(self.next_id.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+
+    fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields(String::new());
+        event.record(&mut fields);
+
+        self.events
+            .lock()
+            .unwrap()
+            .push((*event.metadata().level(), fields.0));
+    }
+
+    fn enter(&self, id: &span::Id) {
+        *self.entered.lock().unwrap() = Some(id.clone());
+    }
+
+    fn exit(&self, _: &span::Id) {
+        *self.entered.lock().unwrap() = None;
+    }
+
+    fn current_span(&self) -> tracing_core::span::Current {
+        match &*self.entered.lock().unwrap() {
+            Some(id) => {
+                let metadata = self.spans.lock().unwrap()[id.into_u64() as usize - 1];
+                tracing_core::span::Current::new(id.clone(), metadata)
+            }
+            None => tracing_core::span::Current::none(),
+        }
+    }
+}
+
+#[test]
+fn publish_is_traced_with_path_and_payload() {
+    let collector = Collector::default();
+    let _sub = bus::Values::subscribe();
+
+    tracing::subscriber::with_default(collector.clone(), || bus::Values::publish(7));
+
+    let events = collector.events.lock().unwrap();
+    assert_eq!(
+        *events,
+        [(
+            Level::TRACE,
+            "message=publish topic=\"Toplevel/Values\" payload=7 ".to_string()
+        )]
+    );
+}
+
+#[test]
+fn lag_is_warned() {
+    let collector = Collector::default();
+    let mut sub = bus::Lagging::subscribe().with_name("lagging");
+
+    for i in 0..5 {
+        bus::Lagging::publish(i);
+    }
+
+    tracing::subscriber::with_default(collector.clone(), || sub.try_recv());
+
+    let events = collector.events.lock().unwrap();
+    assert_eq!(
+        *events,
+        [(
+            Level::WARN,
+            "message=subscriber lagged behind topic=\"Toplevel/Lagging\" subscriber=\"lagging\" missed=3 "
+                .to_string()
+        )]
+    );
+}
+
+#[test]
+fn envelopes_carry_the_publisher_span() {
+    let mut sub = bus::Traced::subscribe();
+
+    let span_id = tracing::subscriber::with_default(Collector::default(), || {
+        let span = tracing::info_span!("publisher");
+        let _entered = span.enter();
+
+        bus::Traced::publish(1);
+
+        span.id()
+    });
+
+    let envelope = sub.try_recv_envelope().unwrap();
+    assert!(span_id.is_some());
+    assert_eq!(envelope.span.id(), span_id);
+}
+
+#[cfg(not(any(feature = "tokio", feature = "async-broadcast")))]
+#[test]
+fn envelopes_from_interrupts_are_untraced() {
+    let mut sub = isr_bus::FromIsr::subscribe();
+
+    tracing::subscriber::with_default(Collector::default(), || {
+        let span = tracing::info_span!("interrupted");
+        let _entered = span.enter();
+
+        isr_bus::FromIsr::publish_from_isr(1);
+        isr_bus::FromIsr::try_publish_nonblocking(2).unwrap();
+    });
+
+    for payload in [1, 2] {
+        let envelope = sub.try_recv_envelope().unwrap();
+        assert_eq!(envelope.payload, payload);
+        assert!(envelope.span.is_none());
+    }
+}
@@ -1,5 +1,10 @@
 use crate::ast::{Ast, SubTopic, Topic};
 use std::collections::HashSet;
+use syn::{
+    visit::{self, Visit},
+    BoundLifetimes, Lifetime, ParenthesizedGenericArguments, TypeBareFn, TypeImplTrait, TypeInfer,
+    TypeReference,
+};
 
 pub struct Analysis {}
 
@@ -13,6 +18,141 @@ fn check_topics_for_uniqueness(topics: &[Topic], errors: &mut Vec<syn::Error>) {
     }
 }
 
+/// Finds the parts of a payload type which are not `'static` or can't be named in a static.
+///
+/// `Clone` and `Send` are checked by the generated code, where the types are known.
+struct PayloadChecker<'a> {
+    errors: &'a mut Vec<syn::Error>,
+}
+
+impl<'ast> Visit<'ast> for PayloadChecker<'_> {
+    fn visit_type_reference(&mut self, reference: &'ast TypeReference) {
+        if reference.lifetime.is_none() {
+            self.errors.push(syn::Error::new_spanned(
+                reference,
+                "References in payloads must be `'static`, e.g. `&'static str`",
+            ));
+        }
+
+        visit::visit_type_reference(self, reference);
+    }
+
+    fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+        if lifetime.ident != "static" {
+            self.errors.push(syn::Error::new_spanned(
+                lifetime,
+                format!("Payloads must be `'static`, `{lifetime}` is not supported"),
+            ));
+        }
+    }
+
+    fn visit_type_impl_trait(&mut self, impl_trait: &'ast TypeImplTrait) {
+        self.errors.push(syn::Error::new_spanned(
+            impl_trait,
+            "`impl Trait` is not supported in payloads",
+        ));
+    }
+
+    fn visit_type_infer(&mut self, infer: &'ast TypeInfer) {
+        self.errors.push(syn::Error::new_spanned(
+            infer,
+            "The payload type must be given in full",
+        ));
+    }
+
+    // Function signatures may borrow, e.g. `fn(&u8)` and `Box<dyn Fn(&u8) + Send>`
+    fn visit_type_bare_fn(&mut self, _: &'ast TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments(&mut self, _: &'ast ParenthesizedGenericArguments) {}
+
+    fn visit_bound_lifetimes(&mut self, _: &'ast BoundLifetimes) {}
+}
+
+fn check_payloads(topics: &[Topic], sub_topics: &[SubTopic], errors: &mut Vec<syn::Error>) {
+    for topic in topics {
+        let mut checker = PayloadChecker { errors };
+        checker.visit_type(&topic.payload);
+
+        if let Some(response) = &topic.response {
+            checker.visit_type(response);
+        }
+    }
+
+    for sub_topic in sub_topics {
+        check_payloads(&sub_topic.ast.topics, &sub_topic.ast.sub_topics, errors);
+    }
+}
+
+fn check_nested_dead_letters(sub_topics: &[SubTopic], errors: &mut Vec<syn::Error>) {
+    for sub_topic in sub_topics {
+        if sub_topic.dead_letters.is_some() {
+            errors.push(syn::Error::new_spanned(
+                &sub_topic.name,
+                "`dead_letters` is only supported on the toplevel sub-topic",
+            ));
+        }
+
+        check_nested_dead_letters(&sub_topic.ast.sub_topics, errors);
+    }
+}
+
+/// The topic modes need `std`, without it only plain topics are supported.
+fn check_std_modes(topics: &[Topic], sub_topics: &[SubTopic], errors: &mut Vec<syn::Error>) {
+    for topic in topics {
+        let mode = if topic.response.is_some() {
+            "Request/response topics"
+        } else if topic.latest {
+            "`[latest]` topics"
+        } else if topic.history > 0 {
+            "`[history = N]` topics"
+        } else if topic.queue {
+            "`[queue]` topics"
+        } else if topic.lossless {
+            "`[lossless]` topics"
+        } else if topic.envelope {
+            "`[envelope]` topics"
+        } else {
+            continue;
+        };
+
+        errors.push(syn::Error::new_spanned(
+            &topic.name,
+            format!("{mode} need a `std` backend, the `critical-section` backend only supports plain topics"),
+        ));
+    }
+
+    for sub_topic in sub_topics {
+        check_std_modes(&sub_topic.ast.topics, &sub_topic.ast.sub_topics, errors);
+    }
+}
+
+fn check_derives(derives: &[syn::Path], errors: &mut Vec<syn::Error>) {
+    for derive in derives {
+        let Some(ident) = derive.segments.last().map(|segment| &segment.ident) else {
+            continue;
+        };
+
+        if ident == "Clone" || ident == "Debug" {
+            errors.push(syn::Error::new_spanned(
+                derive,
+                format!("`{ident}` is always derived on sub-topics"),
+            ));
+        } else if ident == "Default" {
+            errors.push(syn::Error::new_spanned(
+                derive,
+                "`Default` can't be derived on sub-topics, they have no default topic",
+            ));
+        }
+    }
+}
+
+fn check_subtopic_derives(sub_topics: &[SubTopic], errors: &mut Vec<syn::Error>) {
+    for sub_topic in sub_topics {
+        check_derives(&sub_topic.derives, errors);
+        check_subtopic_derives(&sub_topic.ast.sub_topics, errors);
+    }
+}
+
 fn check_subtopics_for_uniqueness(sub_topics: &[SubTopic], errors: &mut Vec<syn::Error>) {
     let mut uniq = HashSet::new();
 
@@ -51,12 +191,29 @@ pub fn analyze(ast: &Ast) -> Result<Analysis, syn::Error> {
         }
     }
 
+    // The dead-letter topic is bus-wide, so it's defined on the toplevel sub-topic
+    for sub_topic in &ast.sub_topics {
+        check_nested_dead_letters(&sub_topic.ast.sub_topics, &mut errors);
+    }
+
+    // The `critical-section` backend is selected by the runtime crate's features
+    if !cfg!(feature = "std") {
+        check_std_modes(&ast.topics, &ast.sub_topics, &mut errors);
+    }
+
+    // Derives already on every sub-topic, or which the sub-topics can't support
+    check_derives(&ast.derives, &mut errors);
+    check_subtopic_derives(&ast.sub_topics, &mut errors);
+
+    // Payloads are stored in statics
+    check_payloads(&ast.topics, &ast.sub_topics, &mut errors);
+
     // Check for doubly defined topic names in each subtopic level
     check_subtopics_for_uniqueness(&ast.sub_topics, &mut errors);
 
     // Collect errors if any and return/halt
     if !errors.is_empty() {
-        let mut err = errors.get(0).unwrap().clone();
+        let mut err = errors.first().unwrap().clone();
         errors.iter().for_each(|e| err.combine(e.clone()));
 
         return Err(err);
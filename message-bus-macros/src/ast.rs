@@ -2,7 +2,8 @@ use proc_macro2::TokenStream as TokenStream2;
 use syn::{
     braced, bracketed,
     parse::{self, Error, Parse, ParseStream},
-    token, Ident, LitInt, Path, Token,
+    punctuated::Punctuated,
+    token, Attribute, Ident, LitInt, Path, Token, Type,
 };
 
 /// Parse a token stream into the AST.
@@ -10,28 +11,263 @@ pub fn parse(items: TokenStream2) -> Result<Ast, Error> {
     syn::parse2(items)
 }
 
-/// Topic definition `name [optional capacity (usize)] => payload`
+/// Topic definition `name [optional capacity (usize), optional modes] => payload`, or
+/// request/response topic definition `name [optional capacity (usize)] => request -> response`
 #[derive(Debug)]
 pub struct Topic {
     pub name: Ident,
-    pub payload: Path,
+    pub payload: Type,
+    /// Response payload of request/response topics
+    pub response: Option<Type>,
     pub capacity: usize,
+    pub latest: bool,
+    /// Number of messages kept for replay, 0 if disabled
+    pub history: usize,
+    /// Work-queue topic, each message is received by one worker
+    pub queue: bool,
+    /// Publishing waits for room instead of overwriting unreceived messages
+    pub lossless: bool,
+    /// Messages are wrapped with a sequence number, timestamp and source
+    pub envelope: bool,
+    /// Maximum number of subscribers, the crate's default if not set
+    pub max_subscribers: Option<usize>,
 }
 
-/// Sub-topic definition `path => { ... }`
+/// Topic options `[capacity, latest, history = N, queue, lossless, envelope, subscribers = N,
+/// dead_letters = N]`, all are optional
+#[derive(Debug)]
+struct TopicOptions {
+    capacity: usize,
+    latest: Option<Ident>,
+    history: Option<Ident>,
+    history_depth: usize,
+    queue: Option<Ident>,
+    lossless: Option<Ident>,
+    envelope: Option<Ident>,
+    subscribers: Option<Ident>,
+    max_subscribers: Option<usize>,
+    dead_letters: Option<Ident>,
+    dead_letters_capacity: usize,
+}
+
+impl TopicOptions {
+    /// The first topic mode which is set, if any.
+    fn mode(&self) -> Option<&Ident> {
+        self.latest
+            .as_ref()
+            .or(self.history.as_ref())
+            .or(self.queue.as_ref())
+            .or(self.lossless.as_ref())
+            .or(self.envelope.as_ref())
+    }
+}
+
+/// Sub-topic definition `#[optional attributes] path [optional subscribers = N, dead_letters = N]
+/// => { ... }`
 #[derive(Debug)]
 pub struct SubTopic {
     pub name: Ident,
     pub module: Ident,
+    /// Attributes of the sub-topic's enum, besides derives
+    pub attrs: Vec<Attribute>,
+    /// Derives of the sub-topic's enum, also derived on its nested sub-topics
+    pub derives: Vec<Path>,
+    /// Maximum number of subscribers, the crate's default if not set
+    pub max_subscribers: Option<usize>,
+    /// Capacity of the bus-wide dead-letter topic, only on the toplevel sub-topic
+    pub dead_letters: Option<usize>,
     pub ast: Ast,
 }
 
 #[derive(Debug)]
 pub struct Ast {
+    /// Attributes of all sub-topic enums from the header `#![...]`, besides derives
+    pub attrs: Vec<Attribute>,
+    /// Derives of all sub-topic enums from the header `#![derive(...)]`
+    pub derives: Vec<Path>,
     pub topics: Vec<Topic>,
     pub sub_topics: Vec<SubTopic>,
 }
 
+/// Split the derived traits out of `attrs`.
+fn split_derives(attrs: Vec<Attribute>) -> parse::Result<(Vec<Attribute>, Vec<Path>)> {
+    let mut others = Vec::new();
+    let mut derives = Vec::new();
+
+    for attr in attrs {
+        if attr.path().is_ident("derive") {
+            derives.extend(attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)?);
+        } else {
+            others.push(attr);
+        }
+    }
+
+    Ok((others, derives))
+}
+
+fn check_option_is_unique(option: &Option<Ident>, ident: &Ident) -> parse::Result<()> {
+    if option.is_some() {
+        return Err(parse::Error::new_spanned(
+            ident,
+            format!("`{ident}` is already defined"),
+        ));
+    }
+
+    Ok(())
+}
+
+fn parse_topic_options(input: ParseStream) -> parse::Result<TopicOptions> {
+    let mut options = TopicOptions {
+        capacity: 1,
+        latest: None,
+        history: None,
+        history_depth: 0,
+        queue: None,
+        lossless: None,
+        envelope: None,
+        subscribers: None,
+        max_subscribers: None,
+        dead_letters: None,
+        dead_letters_capacity: 1,
+    };
+
+    if !input.peek(token::Bracket) {
+        return Ok(options);
+    }
+
+    let content;
+    bracketed!(content in input);
+
+    let mut has_capacity = false;
+
+    loop {
+        if content.is_empty() {
+            break;
+        }
+
+        if content.peek(LitInt) {
+            let lit = content.parse::<LitInt>()?;
+            let cap = lit.base10_parse::<usize>()?;
+
+            if has_capacity {
+                return Err(parse::Error::new_spanned(
+                    &lit,
+                    "Capacity is already defined",
+                ));
+            }
+
+            if cap == 0 {
+                return Err(parse::Error::new_spanned(
+                    &lit,
+                    "Capacity must be larger than 0",
+                ));
+            }
+
+            has_capacity = true;
+            options.capacity = cap;
+        } else {
+            let ident = content.parse::<Ident>()?;
+
+            if ident == "latest" {
+                check_option_is_unique(&options.latest, &ident)?;
+
+                options.latest = Some(ident);
+            } else if ident == "history" {
+                check_option_is_unique(&options.history, &ident)?;
+
+                let _: Token![=] = content.parse()?;
+                let lit = content.parse::<LitInt>()?;
+                let depth = lit.base10_parse::<usize>()?;
+
+                if depth == 0 {
+                    return Err(parse::Error::new_spanned(
+                        &lit,
+                        "History depth must be larger than 0",
+                    ));
+                }
+
+                options.history = Some(ident);
+                options.history_depth = depth;
+            } else if ident == "queue" {
+                check_option_is_unique(&options.queue, &ident)?;
+
+                options.queue = Some(ident);
+            } else if ident == "lossless" {
+                check_option_is_unique(&options.lossless, &ident)?;
+
+                options.lossless = Some(ident);
+            } else if ident == "envelope" {
+                check_option_is_unique(&options.envelope, &ident)?;
+
+                options.envelope = Some(ident);
+            } else if ident == "subscribers" {
+                check_option_is_unique(&options.subscribers, &ident)?;
+
+                let _: Token![=] = content.parse()?;
+                let lit = content.parse::<LitInt>()?;
+                let max = lit.base10_parse::<usize>()?;
+
+                if max == 0 {
+                    return Err(parse::Error::new_spanned(
+                        &lit,
+                        "The maximum number of subscribers must be larger than 0",
+                    ));
+                }
+
+                options.subscribers = Some(ident);
+                options.max_subscribers = Some(max);
+            } else if ident == "dead_letters" {
+                check_option_is_unique(&options.dead_letters, &ident)?;
+
+                // The capacity of the dead-letter topic is optional
+                if content.peek(Token![=]) {
+                    let _: Token![=] = content.parse()?;
+                    let lit = content.parse::<LitInt>()?;
+                    let cap = lit.base10_parse::<usize>()?;
+
+                    if cap == 0 {
+                        return Err(parse::Error::new_spanned(
+                            &lit,
+                            "Capacity must be larger than 0",
+                        ));
+                    }
+
+                    options.dead_letters_capacity = cap;
+                }
+
+                options.dead_letters = Some(ident);
+            } else {
+                return Err(parse::Error::new_spanned(
+                    &ident,
+                    "Unknown topic option, expected a capacity, `latest`, `history = N`, `queue`, `lossless`, `envelope`, `subscribers = N` or `dead_letters = N`",
+                ));
+            }
+        }
+
+        // Don't force trailing commas if it's the end of the options
+        if content.is_empty() {
+            break;
+        }
+
+        let _: Token![,] = content.parse()?;
+    }
+
+    if let Some(queue) = &options.queue {
+        if options.latest.is_some()
+            || options.history.is_some()
+            || options.envelope.is_some()
+            || options.subscribers.is_some()
+        {
+            return Err(parse::Error::new_spanned(
+                queue,
+                "`queue` can't be combined with `latest`, `history`, `envelope` or `subscribers`",
+            ));
+        }
+    }
+
+    Ok(options)
+}
+
 fn parse_ast_nodes(input: ParseStream) -> parse::Result<Ast> {
     let mut topics = Vec::new();
     let mut sub_topics = Vec::new();
@@ -41,11 +277,12 @@ fn parse_ast_nodes(input: ParseStream) -> parse::Result<Ast> {
             break;
         }
 
+        let attrs = input.call(Attribute::parse_outer)?;
         let path: Path = input.parse()?;
 
-        if path.leading_colon.is_some() {
+        if let Some(leading_colon) = path.leading_colon {
             return Err(parse::Error::new_spanned(
-                &path.leading_colon.unwrap(),
+                leading_colon,
                 "Only the forms `Topic` or `sub_topic::SubTopic` is supported, remove the leading colons",
             ));
         }
@@ -53,55 +290,86 @@ fn parse_ast_nodes(input: ParseStream) -> parse::Result<Ast> {
         for segment in &path.segments {
             if !segment.arguments.is_none() {
                 return Err(parse::Error::new_spanned(
-                &segment,
+                segment,
                 "Only the forms `Topic` or `sub_topic::SubTopic` is supported, remove the generic",
             ));
             }
         }
 
-        let capacity = if input.peek(token::Bracket) {
-            let content;
-            bracketed!(content in input);
+        let options = parse_topic_options(input)?;
 
-            let lit = content.parse::<LitInt>()?;
-            let cap = lit.base10_parse::<usize>()?;
+        let _: Token![=>] = input.parse()?;
 
-            if cap == 0 {
+        if let Some(ident) = path.get_ident() {
+            // Parse a topic 'Topic`
+
+            let name = ident.clone();
+            let payload: Type = input.parse()?;
+
+            if let Some(attr) = attrs.first() {
                 return Err(parse::Error::new_spanned(
-                    &lit,
-                    "Capacity must be larger than 0",
+                    attr,
+                    "Attributes are only supported on sub-topics",
                 ));
             }
 
-            cap
-        } else {
-            1
-        };
+            if let Some(dead_letters) = &options.dead_letters {
+                return Err(parse::Error::new_spanned(
+                    dead_letters,
+                    "`dead_letters` is only supported on the toplevel sub-topic",
+                ));
+            }
 
-        let _: Token![=>] = input.parse()?;
+            let response = if input.peek(Token![->]) {
+                let _: Token![->] = input.parse()?;
 
-        if let Some(ident) = path.get_ident() {
-            // Parse a topic 'Topic`
+                if let Some(mode) = options.mode().or(options.subscribers.as_ref()) {
+                    return Err(parse::Error::new_spanned(
+                        mode,
+                        format!("`{mode}` is not supported on request/response topics"),
+                    ));
+                }
 
-            let name = ident.clone();
-            let payload: Path = input.parse()?;
+                Some(input.parse()?)
+            } else {
+                None
+            };
 
             topics.push(Topic {
                 name,
                 payload,
-                capacity,
+                response,
+                capacity: options.capacity,
+                latest: options.latest.is_some(),
+                history: options.history_depth,
+                queue: options.queue.is_some(),
+                lossless: options.lossless.is_some(),
+                envelope: options.envelope.is_some(),
+                max_subscribers: options.max_subscribers,
             });
         } else if path.segments.len() == 2 {
             // Parse a subtopic 'sub_topic::SubTopic`
 
+            if let Some(mode) = options.mode() {
+                return Err(parse::Error::new_spanned(
+                    mode,
+                    format!("`{mode}` is only supported on topics"),
+                ));
+            }
+
             let content;
             braced!(content in input);
 
             let module = path.segments[0].ident.clone();
             let name = path.segments[1].ident.clone();
+            let (attrs, derives) = split_derives(attrs)?;
             sub_topics.push(SubTopic {
                 name,
                 module,
+                attrs,
+                derives,
+                max_subscribers: options.max_subscribers,
+                dead_letters: options.dead_letters.map(|_| options.dead_letters_capacity),
                 ast: parse_ast_nodes(&content)?,
             });
         } else {
@@ -119,14 +387,26 @@ fn parse_ast_nodes(input: ParseStream) -> parse::Result<Ast> {
         let _: Token![,] = input.parse()?;
     }
 
-    Ok(Ast { topics, sub_topics })
+    Ok(Ast {
+        attrs: Vec::new(),
+        derives: Vec::new(),
+        topics,
+        sub_topics,
+    })
 }
 
 impl Parse for Ast {
     fn parse(input: ParseStream<'_>) -> parse::Result<Self> {
+        // The header with the attributes of all sub-topics
+        let (attrs, derives) = split_derives(input.call(Attribute::parse_inner)?)?;
+
         let nodes = parse_ast_nodes(input)?;
 
-        Ok(nodes)
+        Ok(Ast {
+            attrs,
+            derives,
+            ..nodes
+        })
     }
 }
 
@@ -138,7 +418,7 @@ mod test {
 
     fn check_if_topic_exists(node: &Ast, name: &str, payload: &str) -> bool {
         for topic in &node.topics {
-            if topic.name.to_string() == name
+            if topic.name == name
                 && topic.payload.to_token_stream().to_string().replace(" ", "") == payload
             {
                 return true;
@@ -170,11 +450,16 @@ mod test {
             SystemHealth => String, // Primitive payload
             SomeData => some::Data, // Path to payload
             SomeData2 => some::Data2<u32>, // Path to payload
+            Tuple => (u8, u16),
+            Array => [u8; 16],
+            Reference => &'static str,
+            Boxed => Box<dyn std::error::Error + Send + Sync>,
+            Request => (u8, u8) -> [u8; 2],
         );
 
         let ast = parse(tokens).unwrap();
 
-        assert_eq!(ast.topics.len(), 3);
+        assert_eq!(ast.topics.len(), 8);
         assert_eq!(ast.sub_topics.len(), 1);
         assert_eq!(ast.sub_topics[0].ast.topics.len(), 3);
         assert_eq!(ast.sub_topics[0].ast.sub_topics.len(), 1);
@@ -196,5 +481,151 @@ mod test {
         assert!(check_if_topic_exists(&ast, "SystemHealth", "String"));
         assert!(check_if_topic_exists(&ast, "SomeData", "some::Data"));
         assert!(check_if_topic_exists(&ast, "SomeData2", "some::Data2<u32>"));
+        assert!(check_if_topic_exists(&ast, "Tuple", "(u8,u16)"));
+        assert!(check_if_topic_exists(&ast, "Array", "[u8;16]"));
+        assert!(check_if_topic_exists(&ast, "Reference", "&'staticstr"));
+        assert!(check_if_topic_exists(
+            &ast,
+            "Boxed",
+            "Box<dynstd::error::Error+Send+Sync>"
+        ));
+        assert!(check_if_topic_exists(&ast, "Request", "(u8,u8)"));
+        assert_eq!(
+            ast.topics[7]
+                .response
+                .to_token_stream()
+                .to_string()
+                .replace(' ', ""),
+            "[u8;2]"
+        );
+    }
+
+    #[test]
+    fn parse_options() {
+        let tokens = quote!(
+            sub_topic::SubTopic => {
+                Plain => u8,
+                Capacity [10] => u8,
+                Latest [latest] => u8,
+                Both [10, latest] => u8,
+                BothReversed [latest, 20,] => u8,
+                History [history = 16] => u8,
+                All [10, history = 4, latest] => u8,
+                Queue [8, queue] => u8,
+                Request [4] => u8 -> some::Response,
+                Lossless [4, lossless, latest] => u8,
+                LosslessQueue [lossless, queue] => u8,
+                Envelope [4, envelope, latest] => u8,
+                Subscribers [4, subscribers = 2] => u8,
+                bounded::Bounded [subscribers = 3] => {},
+            },
+        );
+
+        let ast = parse(tokens).unwrap();
+        let topics = &ast.sub_topics[0].ast.topics;
+
+        assert_eq!(topics[0].capacity, 1);
+        assert!(!topics[0].latest);
+        assert_eq!(topics[1].capacity, 10);
+        assert!(!topics[1].latest);
+        assert_eq!(topics[2].capacity, 1);
+        assert!(topics[2].latest);
+        assert_eq!(topics[3].capacity, 10);
+        assert!(topics[3].latest);
+        assert_eq!(topics[4].capacity, 20);
+        assert!(topics[4].latest);
+        assert_eq!(topics[4].history, 0);
+        assert_eq!(topics[5].capacity, 1);
+        assert!(!topics[5].latest);
+        assert_eq!(topics[5].history, 16);
+        assert_eq!(topics[6].capacity, 10);
+        assert!(topics[6].latest);
+        assert_eq!(topics[6].history, 4);
+        assert!(!topics[6].queue);
+        assert_eq!(topics[7].capacity, 8);
+        assert!(topics[7].queue);
+        assert!(topics[7].response.is_none());
+        assert_eq!(topics[8].capacity, 4);
+        assert_eq!(
+            topics[8]
+                .response
+                .to_token_stream()
+                .to_string()
+                .replace(' ', ""),
+            "some::Response"
+        );
+        assert!(!topics[8].lossless);
+        assert!(topics[9].lossless);
+        assert!(topics[9].latest);
+        assert!(topics[10].lossless);
+        assert!(topics[10].queue);
+        assert!(!topics[10].envelope);
+        assert!(topics[11].envelope);
+        assert!(topics[11].latest);
+        assert_eq!(topics[11].capacity, 4);
+        assert_eq!(topics[11].max_subscribers, None);
+        assert_eq!(topics[12].max_subscribers, Some(2));
+        assert_eq!(topics[12].capacity, 4);
+
+        let sub_topics = &ast.sub_topics[0].ast.sub_topics;
+
+        assert_eq!(ast.sub_topics[0].max_subscribers, None);
+        assert_eq!(sub_topics[0].max_subscribers, Some(3));
+    }
+
+    #[test]
+    fn parse_dead_letters() {
+        let ast = parse(quote!(bus::Toplevel [dead_letters] => {})).unwrap();
+        assert_eq!(ast.sub_topics[0].dead_letters, Some(1));
+
+        let ast = parse(quote!(bus::Toplevel [subscribers = 2, dead_letters = 8] => {})).unwrap();
+        assert_eq!(ast.sub_topics[0].dead_letters, Some(8));
+        assert_eq!(ast.sub_topics[0].max_subscribers, Some(2));
+
+        let ast = parse(quote!(bus::Toplevel => {})).unwrap();
+        assert_eq!(ast.sub_topics[0].dead_letters, None);
+
+        assert!(parse(quote!(bus::Toplevel => { Data [dead_letters] => u8 })).is_err());
+        assert!(parse(quote!(bus::Toplevel [dead_letters = 0] => {})).is_err());
+    }
+
+    #[test]
+    fn parse_attributes() {
+        let tokens = quote!(
+            #![derive(PartialEq, Eq)]
+            #![allow(missing_docs)]
+
+            bus::Toplevel => {
+                #[derive(Hash)]
+                #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+                #[derive(schemars::JsonSchema)]
+                sub::SubTopic => {
+                    Data => u8,
+                },
+            },
+        );
+
+        let ast = parse(tokens).unwrap();
+        let to_strings = |paths: &[Path]| {
+            paths
+                .iter()
+                .map(|path| path.to_token_stream().to_string().replace(' ', ""))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(to_strings(&ast.derives), ["PartialEq", "Eq"]);
+        assert_eq!(ast.attrs.len(), 1);
+        assert!(ast.sub_topics[0].derives.is_empty());
+
+        let sub_topic = &ast.sub_topics[0].ast.sub_topics[0];
+        assert_eq!(
+            to_strings(&sub_topic.derives),
+            ["Hash", "schemars::JsonSchema"]
+        );
+        assert_eq!(sub_topic.attrs.len(), 1);
+        assert!(sub_topic.attrs[0].path().is_ident("cfg_attr"));
+
+        assert!(parse(quote!(bus::Toplevel => { #[derive(Hash)] Data => u8 })).is_err());
+        assert!(parse(quote!(#![derive(Hash)] #![derive] bus::Toplevel => {})).is_err());
     }
 }
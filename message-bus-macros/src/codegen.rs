@@ -3,21 +3,31 @@ use crate::{
     ast::{Ast, SubTopic, Topic},
 };
 use proc_macro2::{Span, TokenStream as TokenStream2};
-use quote::quote;
-use syn::Ident;
+use quote::{quote, quote_spanned};
+use syn::{spanned::Spanned, Attribute, Ident, Path, Type};
 
-fn make_topics_enum(name: &Ident, topics: &[Topic], sub_topics: &[SubTopic]) -> TokenStream2 {
+fn make_topics_enum(
+    name: &Ident,
+    topics: &[Topic],
+    sub_topics: &[SubTopic],
+    attrs: &[&Attribute],
+    derives: &[Path],
+) -> TokenStream2 {
     let mut arms = Vec::new();
+    let mut clone_arms = Vec::new();
 
-    for topic in topics {
+    // Request/response topics are not forwarded to parent topics
+    for topic in topics.iter().filter(|topic| topic.response.is_none()) {
         let tn = &topic.name;
         let tp = &topic.payload;
         let doc = format!("Type-level definition of the `{tn}` topic");
+        let clone_payload = clone_payload(tp, quote!(payload));
 
         arms.push(quote!(
             #[doc = #doc]
             #tn(#tp)
         ));
+        clone_arms.push(quote!(Self::#tn(payload) => Self::#tn(#clone_payload)));
     }
 
     for sub_topic in sub_topics {
@@ -28,15 +38,431 @@ fn make_topics_enum(name: &Ident, topics: &[Topic], sub_topics: &[SubTopic]) ->
             #[doc = #doc]
             #tn(#tn)
         ));
+        clone_arms.push(quote!(Self::#tn(sub_topic) => Self::#tn(sub_topic.clone())));
     }
 
+    // Not derived, so payloads which are not `Clone` are only reported at their type. Without
+    // variants there's nothing to match on but the dereferenced enum
+    let matched = if clone_arms.is_empty() {
+        quote!(*self)
+    } else {
+        quote!(self)
+    };
+
+    // Externally tagged by the topic names, which makes the representation stable as long as the
+    // topics are not renamed
+    let serde = if cfg!(feature = "serde") {
+        quote!(
+            #[derive(
+                ::make_message_bus::__private::serde::Serialize,
+                ::make_message_bus::__private::serde::Deserialize,
+            )]
+            #[serde(crate = "::make_message_bus::__private::serde")]
+        )
+    } else {
+        quote!()
+    };
+
     let doc = format!("Type-level definition of all topics in `{name}`");
     quote!(
         #[doc = #doc]
-        #[derive(Clone, Debug)]
+        #[derive(Debug, #(#derives),*)]
+        #serde
+        #(#attrs)*
         pub enum #name {
             #(#arms),*
         }
+
+        impl ::core::clone::Clone for #name {
+            fn clone(&self) -> Self {
+                match #matched {
+                    #(#clone_arms),*
+                }
+            }
+        }
+    )
+}
+
+/// Recording and replaying the messages of a sub-topic, `path` is the full path of the sub-topic.
+fn make_recordable(
+    name: &Ident,
+    topics: &[Topic],
+    sub_topics: &[SubTopic],
+    path: &str,
+) -> TokenStream2 {
+    let mut path_arms = Vec::new();
+    let mut encode_arms = Vec::new();
+    let mut decode_arms = Vec::new();
+    let mut publish_arms = Vec::new();
+
+    for topic in topics.iter().filter(|topic| topic.response.is_none()) {
+        let tn = &topic.name;
+        let topic_path = format!("{path}/{tn}");
+        let publish = if topic.lossless {
+            quote!(#tn::publish(payload).await)
+        } else {
+            quote!(#tn::publish(payload))
+        };
+
+        path_arms.push(quote!(Self::#tn(_) => #topic_path));
+        encode_arms.push(quote!(
+            Self::#tn(payload) => ::make_message_bus::__private::serde_json::to_vec(payload)
+        ));
+        decode_arms.push(quote!(
+            #topic_path => ::make_message_bus::__private::serde_json::from_slice(payload)
+                .map(Self::#tn)
+                .map_err(::make_message_bus::ReplayError::Payload)
+        ));
+        publish_arms.push(quote!(Self::#tn(payload) => #publish));
+    }
+
+    // Nested sub-topics handle the paths of their own topics
+    for sub_topic in sub_topics {
+        let tn = &sub_topic.name;
+        let prefix = format!("{path}/{tn}/");
+
+        path_arms.push(quote!(Self::#tn(inner) => ::make_message_bus::Recordable::path(inner)));
+        encode_arms.push(quote!(
+            Self::#tn(inner) => ::make_message_bus::Recordable::encode_payload(inner)
+        ));
+        decode_arms.push(quote!(
+            path if path.starts_with(#prefix) => {
+                ::make_message_bus::Recordable::decode(path, payload).map(Self::#tn)
+            }
+        ));
+        publish_arms.push(quote!(
+            Self::#tn(inner) => ::make_message_bus::Recordable::publish(inner).await
+        ));
+    }
+
+    // A reference to an enum without variants is not uninhabited, so the enum is matched on
+    let matched = if path_arms.is_empty() {
+        quote!(*self)
+    } else {
+        quote!(self)
+    };
+
+    quote!(
+        impl ::make_message_bus::Recordable for #name {
+            fn path(&self) -> &'static str {
+                match #matched {
+                    #(#path_arms,)*
+                }
+            }
+
+            fn encode_payload(
+                &self,
+            ) -> ::make_message_bus::__private::serde_json::Result<::std::vec::Vec<u8>> {
+                match #matched {
+                    #(#encode_arms,)*
+                }
+            }
+
+            fn decode(
+                path: &str,
+                payload: &[u8],
+            ) -> ::core::result::Result<Self, ::make_message_bus::ReplayError> {
+                match path {
+                    #(#decode_arms,)*
+                    _ => ::core::result::Result::Err(::make_message_bus::ReplayError::UnknownTopic(path.into())),
+                }
+            }
+
+            async fn publish(self) -> ::make_message_bus::PublishReport {
+                match self {
+                    #(#publish_arms,)*
+                }
+            }
+        }
+    )
+}
+
+fn make_publish_fns(
+    topic: &Topic,
+    topic_static: &Ident,
+    publish_parent_topics: &[TokenStream2],
+    isr_parent_topics: &[TokenStream2],
+    path: &str,
+) -> TokenStream2 {
+    let topic_name = &topic.name;
+    let topic_payload = &topic.payload;
+    let clone_payload = clone_payload(topic_payload, quote!(&payload));
+
+    // Not traced from interrupts, where the tracing subscriber could block
+    let trace_publish = if cfg!(feature = "tracing") {
+        quote!(::make_message_bus::__private::tracing::trace!(
+            topic = #path,
+            payload = ?payload,
+            "publish"
+        );)
+    } else {
+        quote!()
+    };
+
+    // Envelope topics wrap the payload before publishing, parent topics get the bare payload.
+    // From interrupts the current span is not captured, as the tracing subscriber could block
+    let (message, lossless_message, isr_message, isr_lossless_message) = if topic.envelope {
+        (
+            quote!(::make_message_bus::Envelope::new(payload, source)),
+            quote!(::make_message_bus::Envelope::new(#clone_payload, source)),
+            quote!(::make_message_bus::Envelope::new_untraced(
+                payload,
+                ::core::option::Option::None
+            )),
+            quote!(::make_message_bus::Envelope::new_untraced(
+                #clone_payload,
+                ::core::option::Option::None
+            )),
+        )
+    } else {
+        (
+            quote!(payload),
+            clone_payload.clone(),
+            quote!(payload),
+            clone_payload,
+        )
+    };
+    let (full_payload, would_block_payload) = if topic.envelope {
+        (
+            quote!(.map_err(|full| ::make_message_bus::Full(full.0.payload))),
+            quote!(.map_err(|err| ::make_message_bus::WouldBlock(err.0.payload))),
+        )
+    } else {
+        (quote!(), quote!())
+    };
+
+    // The publish functions of envelope topics take the source and are wrapped below
+    let (vis, publish, try_publish, source_arg) = if topic.envelope {
+        (
+            quote!(),
+            quote!(publish_envelope),
+            quote!(try_publish_envelope),
+            quote!(source: ::core::option::Option<&'static str>,),
+        )
+    } else {
+        (quote!(pub), quote!(publish), quote!(try_publish), quote!())
+    };
+
+    let publish_fns = if topic.lossless {
+        let doc_pub = format!(
+            "Publish to the `{topic_name}` topic, waits until all subscribers have room for the message."
+        );
+        let doc_try_pub = format!(
+            "Try to publish to the `{topic_name}` topic, fails if any subscriber has no room for the message."
+        );
+
+        // Parent topics are only published to once the message is accepted on the topic
+        let mut fns = quote!(
+            #[doc = #doc_pub]
+            #vis async fn #publish(payload: #topic_payload, #source_arg) -> ::make_message_bus::PublishReport {
+                #trace_publish
+                let receivers = #topic_static.publish_lossless(#lossless_message).await;
+                let parent_receivers = #(#publish_parent_topics)+*;
+
+                ::make_message_bus::PublishReport { receivers, parent_receivers }
+            }
+
+            #[doc = #doc_try_pub]
+            #vis fn #try_publish(
+                payload: #topic_payload,
+                #source_arg
+            ) -> ::core::result::Result<::make_message_bus::PublishReport, ::make_message_bus::Full<#topic_payload>> {
+                #trace_publish
+                let receivers = #topic_static.try_publish(#lossless_message)#full_payload?;
+                let parent_receivers = #(#publish_parent_topics)+*;
+
+                ::core::result::Result::Ok(::make_message_bus::PublishReport { receivers, parent_receivers })
+            }
+        );
+
+        if topic.envelope {
+            let doc_pub_from = format!(
+                "Publish to the `{topic_name}` topic from `source`, waits until all subscribers have room for the message."
+            );
+            let doc_try_pub_from = format!(
+                "Try to publish to the `{topic_name}` topic from `source`, fails if any subscriber has no room for the message."
+            );
+
+            fns = quote!(
+                #fns
+
+                #[doc = #doc_pub]
+                pub async fn publish(payload: #topic_payload) -> ::make_message_bus::PublishReport {
+                    Self::publish_envelope(payload, ::core::option::Option::None).await
+                }
+
+                #[doc = #doc_pub_from]
+                pub async fn publish_from(
+                    source: &'static str,
+                    payload: #topic_payload,
+                ) -> ::make_message_bus::PublishReport {
+                    Self::publish_envelope(payload, ::core::option::Option::Some(source)).await
+                }
+
+                #[doc = #doc_try_pub]
+                pub fn try_publish(
+                    payload: #topic_payload,
+                ) -> ::core::result::Result<::make_message_bus::PublishReport, ::make_message_bus::Full<#topic_payload>> {
+                    Self::try_publish_envelope(payload, ::core::option::Option::None)
+                }
+
+                #[doc = #doc_try_pub_from]
+                pub fn try_publish_from(
+                    source: &'static str,
+                    payload: #topic_payload,
+                ) -> ::core::result::Result<::make_message_bus::PublishReport, ::make_message_bus::Full<#topic_payload>> {
+                    Self::try_publish_envelope(payload, ::core::option::Option::Some(source))
+                }
+            );
+        }
+
+        fns
+    } else {
+        let doc_pub = format!("Publish to the `{topic_name}` topic.");
+
+        let mut fns = quote!(
+            #[doc = #doc_pub]
+            #vis fn #publish(payload: #topic_payload, #source_arg) -> ::make_message_bus::PublishReport {
+                #trace_publish
+                let parent_receivers = #(#publish_parent_topics)+*;
+                let receivers = #topic_static.publish(#message);
+
+                ::make_message_bus::PublishReport { receivers, parent_receivers }
+            }
+        );
+
+        if topic.envelope {
+            let doc_pub_from = format!("Publish to the `{topic_name}` topic from `source`.");
+
+            fns = quote!(
+                #fns
+
+                #[doc = #doc_pub]
+                pub fn publish(payload: #topic_payload) -> ::make_message_bus::PublishReport {
+                    Self::publish_envelope(payload, ::core::option::Option::None)
+                }
+
+                #[doc = #doc_pub_from]
+                pub fn publish_from(
+                    source: &'static str,
+                    payload: #topic_payload,
+                ) -> ::make_message_bus::PublishReport {
+                    Self::publish_envelope(payload, ::core::option::Option::Some(source))
+                }
+            );
+        }
+
+        fns
+    };
+
+    let doc_try_pub_nb = format!(
+        "Publish to the `{topic_name}` topic without blocking or allocating, fails if the message can't be published immediately."
+    );
+    let doc_pub_isr = format!(
+        "Publish to the `{topic_name}` topic from an interrupt or signal handler, the message is dropped if it can't be published immediately."
+    );
+    let doc_count = format!("Number of subscribers of the `{topic_name}` topic.");
+    let doc_has = format!("Checks if the `{topic_name}` topic has any subscribers.");
+    let doc_stats = format!("Snapshot of the statistics of the `{topic_name}` topic.");
+
+    // The `tokio` and `async-broadcast` channels always take their lock, which can't be done from
+    // an interrupt
+    let isr_fns = if cfg!(feature = "locking-backend") {
+        quote!()
+    } else {
+        quote!(
+            #[doc = #doc_try_pub_nb]
+            pub fn try_publish_nonblocking(
+                payload: #topic_payload,
+            ) -> ::core::result::Result<::make_message_bus::PublishReport, ::make_message_bus::WouldBlock<#topic_payload>> {
+                // Parent topics are only published to once the message is accepted on the topic
+                let receivers = #topic_static.try_publish_nonblocking(#isr_lossless_message)#would_block_payload?;
+                let parent_receivers = #(#isr_parent_topics)+*;
+
+                ::core::result::Result::Ok(::make_message_bus::PublishReport { receivers, parent_receivers })
+            }
+
+            #[doc = #doc_pub_isr]
+            pub fn publish_from_isr(payload: #topic_payload) -> ::make_message_bus::PublishReport {
+                let parent_receivers = #(#isr_parent_topics)+*;
+                let receivers = #topic_static.publish_from_isr(#isr_message);
+
+                ::make_message_bus::PublishReport { receivers, parent_receivers }
+            }
+        )
+    };
+
+    quote!(
+        #publish_fns
+
+        #isr_fns
+
+        #[doc = #doc_count]
+        pub fn receiver_count() -> usize {
+            #topic_static.receiver_count()
+        }
+
+        #[doc = #doc_has]
+        pub fn has_subscribers() -> bool {
+            #topic_static.has_subscribers()
+        }
+
+        #[doc = #doc_stats]
+        pub fn stats() -> ::make_message_bus::TopicStats {
+            #topic_static.stats()
+        }
+    )
+}
+
+/// The payload type resolved through a check of the `bounds` it needs, for the topic's static. An
+/// invalid payload is reported once at its type, and the code using the static is not checked
+/// further.
+fn checked_payload(ty: &Type, bounds: &str) -> TokenStream2 {
+    let bounds = Ident::new(bounds, Span::call_site());
+
+    quote_spanned!(ty.span()=>
+        <::make_message_bus::__private::Checked<#ty> as ::make_message_bus::__private::#bounds>::Type
+    )
+}
+
+/// The hidden alias of a topic's payload type.
+fn payload_alias(topic_name: &Ident) -> Ident {
+    Ident::new(&format!("__PAYLOAD_{topic_name}"), Span::call_site())
+}
+
+/// Checks of the payloads of all topics in the sub-topic and its nested sub-topics, relative to
+/// the sub-topic's module. Each is spanned at the payload's type, so it's the same error as the
+/// check of the topic's static.
+fn collect_payload_checks(
+    sub_topic: &SubTopic,
+    modules: &mut Vec<Ident>,
+    checks: &mut Vec<TokenStream2>,
+) {
+    for topic in sub_topic
+        .ast
+        .topics
+        .iter()
+        .filter(|topic| topic.response.is_none())
+    {
+        let payload_alias = payload_alias(&topic.name);
+
+        checks.push(quote_spanned!(topic.payload.span()=>
+            <::make_message_bus::__private::Checked<#(#modules::)* #payload_alias> as ::make_message_bus::__private::TopicPayload>::Type
+        ));
+    }
+
+    for st in &sub_topic.ast.sub_topics {
+        modules.push(st.module.clone());
+        collect_payload_checks(st, modules, checks);
+        modules.pop();
+    }
+}
+
+/// Clone the payload referenced by `payload` through the check of its bounds, so a payload which
+/// is not `Clone` is only reported at its type.
+fn clone_payload(ty: &Type, payload: TokenStream2) -> TokenStream2 {
+    quote_spanned!(ty.span()=>
+        <::make_message_bus::__private::Checked<#ty> as ::make_message_bus::__private::TopicPayload>::clone(#payload)
     )
 }
 
@@ -50,10 +476,216 @@ fn codegen_topics(topics: &[Topic], subtopic_tracker: &mut SubTopicTracker) -> V
         let topic_capacity = &topic.capacity;
 
         let doc_topic = format!("Handle to the `{topic_name}` topic.");
-        let doc_sub = format!("Subscribe to the `{topic_name}` topic.");
-        let doc_pub = format!("Publish to the `{topic_name}` topic.");
 
-        let publish_parent_topics = subtopic_tracker.to_parent_publishes(topic_name);
+        if let Some(topic_response) = &topic.response {
+            let doc_req =
+                format!("Send a request on the `{topic_name}` topic and wait for the response.");
+            let doc_req_timeout = format!(
+                "Send a request on the `{topic_name}` topic and wait for the response, giving up after `timeout`."
+            );
+            let doc_responder =
+                format!("Take a responder for requests on the `{topic_name}` topic.");
+            let doc_serve = format!("Serve requests on the `{topic_name}` topic with `f` forever.");
+            let checked_request = checked_payload(topic_payload, "RpcPayload");
+            let checked_response = checked_payload(topic_response, "RpcPayload");
+
+            tokens.push(quote!(
+                #[doc = #doc_topic]
+                pub struct #topic_name;
+
+                #[doc(hidden)]
+                #[allow(non_upper_case_globals)]
+                static #topic_static: ::make_message_bus::Rpc<#checked_request, #checked_response> = ::make_message_bus::Rpc::new::<#topic_capacity>();
+
+                impl #topic_name {
+                    #[doc = #doc_req]
+                    pub async fn request(
+                        request: #topic_payload,
+                    ) -> ::core::result::Result<#topic_response, ::make_message_bus::RpcError> {
+                        #topic_static.request(request).await
+                    }
+
+                    #[doc = #doc_req_timeout]
+                    pub async fn request_timeout(
+                        request: #topic_payload,
+                        timeout: ::core::time::Duration,
+                    ) -> ::core::result::Result<#topic_response, ::make_message_bus::RpcError> {
+                        #topic_static.request_timeout(request, timeout).await
+                    }
+
+                    #[doc = #doc_responder]
+                    pub fn take_responder() -> ::make_message_bus::Responder<#topic_payload, #topic_response> {
+                        #topic_static.take_responder()
+                    }
+
+                    #[doc = #doc_serve]
+                    pub async fn serve(f: impl FnMut(#topic_payload) -> #topic_response) {
+                        #topic_static.take_responder().serve(f).await
+                    }
+                }
+            ));
+
+            continue;
+        }
+
+        let checked = checked_payload(topic_payload, "TopicPayload");
+        let payload_alias = payload_alias(topic_name);
+
+        // Named by the sub-topics containing the topic, to check the payload where it's written
+        tokens.push(quote!(
+            #[doc(hidden)]
+            #[allow(non_camel_case_types)]
+            pub type #payload_alias = #topic_payload;
+        ));
+        let publish_parent_topics = subtopic_tracker.to_parent_publishes(topic, "publish");
+        let isr_parent_topics = subtopic_tracker.to_parent_publishes(topic, "publish_from_isr");
+        let publish_fns = make_publish_fns(
+            topic,
+            &topic_static,
+            &publish_parent_topics,
+            &isr_parent_topics,
+            &subtopic_tracker.path(Some(topic_name)),
+        );
+
+        let doc_dropped = format!(
+            "Number of messages dropped by `publish_from_isr` on the `{topic_name}` topic since last time this was called."
+        );
+        let dropped_fn = if cfg!(feature = "locking-backend") {
+            quote!()
+        } else {
+            quote!(
+                #[doc = #doc_dropped]
+                pub fn dropped_publishes() -> u64 {
+                    #topic_static.dropped_publishes()
+                }
+            )
+        };
+
+        if topic.queue {
+            let doc_worker = format!("Take a worker for the `{topic_name}` work-queue topic.");
+            let doc_lost =
+                format!("Number of messages dropped from the `{topic_name}` work-queue topic since last time this was called.");
+
+            tokens.push(quote!(
+                #[doc = #doc_topic]
+                pub struct #topic_name;
+
+                #[doc(hidden)]
+                #[allow(non_upper_case_globals)]
+                static #topic_static: ::make_message_bus::Queue<#checked> = ::make_message_bus::Queue::new::<#topic_capacity>();
+
+                impl #topic_name {
+                    #[doc = #doc_worker]
+                    pub fn take_worker() -> ::make_message_bus::Worker<#topic_payload> {
+                        #topic_static.take_worker()
+                    }
+
+                    #[doc = #doc_lost]
+                    pub fn messages_lost() -> u64 {
+                        #topic_static.messages_lost()
+                    }
+
+                    #dropped_fn
+
+                    #publish_fns
+                }
+            ));
+
+            continue;
+        }
+
+        let retain = topic.history.max(topic.latest as usize);
+        let topic_subscribers = max_subscribers(topic.max_subscribers);
+        let mut topic_new = quote!(::make_message_bus::Topic::new());
+
+        if retain > 0 {
+            topic_new = quote!(#topic_new.retain(#retain));
+        }
+
+        if topic.lossless {
+            topic_new = quote!(#topic_new.lossless());
+        }
+
+        let lag_reporting = subtopic_tracker.to_lag_reporting(Some(topic_name));
+        topic_new = quote!(#topic_new #lag_reporting);
+
+        // Envelope topics store the wrapped payload, subscribers unwrap it
+        let (static_payload, subscriber, wrap_subscriber, unwrap_latest) = if topic.envelope {
+            topic_new = quote!(#topic_new.sequenced());
+
+            (
+                quote!(::make_message_bus::Envelope<#checked>),
+                quote!(::make_message_bus::EnvelopeSubscriber<#topic_payload>),
+                quote!(::make_message_bus::EnvelopeSubscriber::new),
+                quote!(.map(|envelope| envelope.payload)),
+            )
+        } else {
+            (
+                checked.clone(),
+                quote!(::make_message_bus::Subscriber<#topic_payload>),
+                quote!(),
+                quote!(),
+            )
+        };
+
+        let mut subscribe_fns = Vec::new();
+        let subscribe = if topic.latest {
+            quote!(#topic_static.subscribe_with_latest())
+        } else {
+            quote!(#topic_static.subscribe())
+        };
+
+        if topic.latest {
+            let doc_sub = format!(
+                "Subscribe to the `{topic_name}` topic, the latest published value is received first."
+            );
+            let doc_get = format!("Get the latest value published to the `{topic_name}` topic.");
+
+            subscribe_fns.push(quote!(
+                #[doc = #doc_sub]
+                pub fn subscribe() -> #subscriber {
+                    #wrap_subscriber(#subscribe)
+                }
+
+                #[doc = #doc_get]
+                pub fn get() -> ::core::option::Option<#topic_payload> {
+                    #topic_static.latest()#unwrap_latest
+                }
+            ));
+        } else {
+            let doc_sub = format!("Subscribe to the `{topic_name}` topic.");
+
+            subscribe_fns.push(quote!(
+                #[doc = #doc_sub]
+                pub fn subscribe() -> #subscriber {
+                    #wrap_subscriber(#subscribe)
+                }
+            ));
+        }
+
+        let doc_sub_with = format!(
+            "Subscribe to the `{topic_name}` topic, with `lag_policy` deciding what happens when the subscriber lags behind."
+        );
+
+        subscribe_fns.push(quote!(
+            #[doc = #doc_sub_with]
+            pub fn subscribe_with(lag_policy: ::make_message_bus::LagPolicy) -> #subscriber {
+                #wrap_subscriber(#subscribe.with_lag_policy(lag_policy))
+            }
+        ));
+
+        if topic.history > 0 {
+            let doc_sub = format!(
+                "Subscribe to the `{topic_name}` topic, up to the last {retain} published values are received first."
+            );
+
+            subscribe_fns.push(quote!(
+                #[doc = #doc_sub]
+                pub fn subscribe_with_history() -> #subscriber {
+                    #wrap_subscriber(#topic_static.subscribe_with_history())
+                }
+            ));
+        }
 
         tokens.push(quote!(
             #[doc = #doc_topic]
@@ -61,20 +693,14 @@ fn codegen_topics(topics: &[Topic], subtopic_tracker: &mut SubTopicTracker) -> V
 
             #[doc(hidden)]
             #[allow(non_upper_case_globals)]
-            static #topic_static: ::make_message_bus::Topic<#topic_payload> = ::make_message_bus::Topic::new::<#topic_capacity>();
+            static #topic_static: ::make_message_bus::Topic<#static_payload, #topic_capacity, #topic_subscribers> = #topic_new;
 
             impl #topic_name {
-                #[doc = #doc_sub]
-                pub fn subscribe() -> ::make_message_bus::Subscriber<#topic_payload> {
-                    #topic_static.subscribe()
-                }
+                #(#subscribe_fns)*
 
-                #[doc = #doc_pub]
-                pub fn publish(payload: #topic_payload) {
-                    #(#publish_parent_topics)*
+                #publish_fns
 
-                    #topic_static.publish(payload);
-                }
+                #dropped_fn
             }
         ));
     }
@@ -85,21 +711,50 @@ fn codegen_topics(topics: &[Topic], subtopic_tracker: &mut SubTopicTracker) -> V
 fn codegen_subtopics(
     sub_topics: &[SubTopic],
     subtopic_tracker: &mut SubTopicTracker,
+    header_attrs: &[Attribute],
+    parent_derives: &[Path],
 ) -> Vec<TokenStream2> {
     let mut tokens = Vec::new();
 
     for sub_topic in sub_topics {
         subtopic_tracker.add_subtopic(sub_topic.name.clone());
 
+        // Derives are inherited by the nested sub-topics, which are payloads of this one
+        let mut derives = parent_derives.to_vec();
+
+        for derive in &sub_topic.derives {
+            if !derives.contains(derive) {
+                derives.push(derive.clone());
+            }
+        }
+
+        let attrs: Vec<_> = header_attrs.iter().chain(&sub_topic.attrs).collect();
         let topic_enum = make_topics_enum(
             &sub_topic.name,
             &sub_topic.ast.topics,
             &sub_topic.ast.sub_topics,
+            &attrs,
+            &derives,
         );
+        let recordable = if cfg!(feature = "record") {
+            make_recordable(
+                &sub_topic.name,
+                &sub_topic.ast.topics,
+                &sub_topic.ast.sub_topics,
+                &subtopic_tracker.path(None),
+            )
+        } else {
+            quote!()
+        };
         let topics = codegen_topics(&sub_topic.ast.topics, subtopic_tracker);
 
         // For the next sub topic, recurse down the tree until bottom is reached
-        let sub_topic_tokens = codegen_subtopics(&sub_topic.ast.sub_topics, subtopic_tracker);
+        let sub_topic_tokens = codegen_subtopics(
+            &sub_topic.ast.sub_topics,
+            subtopic_tracker,
+            header_attrs,
+            &derives,
+        );
         let sub_topic_name = &sub_topic.name;
         let sub_topic_module = &sub_topic.module;
         let sub_topic_doc1 = format!(
@@ -108,6 +763,8 @@ fn codegen_subtopics(
         let sub_topic_doc2 =
             format!("All topics in the `{sub_topic_module}::{sub_topic_name}` subtopic");
         let sub_topic_static = Ident::new(&format!("__TOPIC_{sub_topic_name}"), Span::call_site());
+        let mut payload_checks = Vec::new();
+        collect_payload_checks(sub_topic, &mut Vec::new(), &mut payload_checks);
 
         let pub_use = if !subtopic_tracker.at_root() {
             quote!(pub use #sub_topic_module::#sub_topic_name;)
@@ -116,9 +773,26 @@ fn codegen_subtopics(
         };
 
         let doc_sub = format!("Subscribe to the `{sub_topic_name}` sub-topic.");
+        let doc_sub_with = format!(
+            "Subscribe to the `{sub_topic_name}` sub-topic, with `lag_policy` deciding what happens when the subscriber lags behind."
+        );
+        let doc_count = format!("Number of subscribers of the `{sub_topic_name}` sub-topic.");
+        let doc_has = format!("Checks if the `{sub_topic_name}` sub-topic has any subscribers.");
+        let doc_stats = format!("Snapshot of the statistics of the `{sub_topic_name}` sub-topic.");
 
         let mut capacity = 0;
         find_total_capacity(sub_topic, &mut capacity);
+        let sub_topic_subscribers = max_subscribers(sub_topic.max_subscribers);
+        let sub_topic_lag_reporting = subtopic_tracker.to_lag_reporting(None);
+        let dead_letters = sub_topic
+            .dead_letters
+            .map(make_dead_letters)
+            .unwrap_or_default();
+        let bus_stats = if subtopic_tracker.depth() == 1 {
+            make_bus_stats(sub_topic)
+        } else {
+            quote!()
+        };
 
         tokens.push(quote!(
             #pub_use
@@ -127,16 +801,48 @@ fn codegen_subtopics(
             pub mod #sub_topic_module {
                 #[doc(hidden)]
                 #[allow(non_upper_case_globals)]
-                static #sub_topic_static: ::make_message_bus::Topic<#sub_topic_name> = ::make_message_bus::Topic::new::<#capacity>();
+                static #sub_topic_static: ::make_message_bus::Topic<
+                    <((#(#payload_checks,)*), #sub_topic_name) as ::make_message_bus::__private::AfterChecks>::Type,
+                    #capacity,
+                    #sub_topic_subscribers,
+                > = ::make_message_bus::Topic::new() #sub_topic_lag_reporting;
+
+                #dead_letters
+
+                #bus_stats
 
                 #[doc = #sub_topic_doc2]
                 #topic_enum
 
+                #recordable
+
                 impl #sub_topic_name {
                     #[doc = #doc_sub]
                     pub fn subscribe() -> ::make_message_bus::Subscriber<#sub_topic_name> {
                         #sub_topic_static.subscribe()
                     }
+
+                    #[doc = #doc_sub_with]
+                    pub fn subscribe_with(
+                        lag_policy: ::make_message_bus::LagPolicy,
+                    ) -> ::make_message_bus::Subscriber<#sub_topic_name> {
+                        #sub_topic_static.subscribe().with_lag_policy(lag_policy)
+                    }
+
+                    #[doc = #doc_count]
+                    pub fn receiver_count() -> usize {
+                        #sub_topic_static.receiver_count()
+                    }
+
+                    #[doc = #doc_has]
+                    pub fn has_subscribers() -> bool {
+                        #sub_topic_static.has_subscribers()
+                    }
+
+                    #[doc = #doc_stats]
+                    pub fn stats() -> ::make_message_bus::TopicStats {
+                        #sub_topic_static.stats()
+                    }
                 }
 
                 #(#topics)*
@@ -150,11 +856,132 @@ fn codegen_subtopics(
     tokens
 }
 
+/// Collect the paths and handles of all topics in the sub-topic, relative to the toplevel module.
+fn collect_stats(
+    sub_topic: &SubTopic,
+    modules: &mut Vec<Ident>,
+    path: &mut Vec<String>,
+    stats: &mut Vec<TokenStream2>,
+) {
+    path.push(sub_topic.name.to_string());
+
+    let sub_topic_name = &sub_topic.name;
+    let sub_topic_path = path.join("/");
+    stats.push(quote!((#sub_topic_path, #(#modules::)* #sub_topic_name::stats())));
+
+    // Request/response topics have no statistics
+    for topic in sub_topic
+        .ast
+        .topics
+        .iter()
+        .filter(|topic| topic.response.is_none())
+    {
+        let topic_name = &topic.name;
+        let topic_path = format!("{sub_topic_path}/{topic_name}");
+
+        stats.push(quote!((#topic_path, #(#modules::)* #topic_name::stats())));
+    }
+
+    for st in &sub_topic.ast.sub_topics {
+        modules.push(st.module.clone());
+        collect_stats(st, modules, path, stats);
+        modules.pop();
+    }
+
+    path.pop();
+}
+
+/// The bus-wide `stats()`, walking the whole topic tree.
+fn make_bus_stats(toplevel: &SubTopic) -> TokenStream2 {
+    let mut stats = Vec::new();
+    collect_stats(toplevel, &mut Vec::new(), &mut Vec::new(), &mut stats);
+
+    let count = stats.len();
+    let toplevel_name = &toplevel.name;
+
+    let render_metrics = if cfg!(feature = "prometheus") {
+        quote!(
+            impl #toplevel_name {
+                /// Render the statistics of all topics on the bus in Prometheus text format,
+                /// labeled with the full path of the topics.
+                pub fn render_metrics() -> ::std::string::String {
+                    stats().render_prometheus()
+                }
+            }
+        )
+    } else {
+        quote!()
+    };
+
+    quote!(
+        /// Snapshot of the statistics of all topics on the bus, keyed by their full path.
+        pub fn stats() -> ::make_message_bus::BusStats<#count> {
+            ::make_message_bus::BusStats {
+                topics: [#(#stats),*],
+            }
+        }
+
+        #render_metrics
+    )
+}
+
+/// The bus-wide dead-letter topic, receiving the messages missed by any subscriber.
+fn make_dead_letters(capacity: usize) -> TokenStream2 {
+    let subscribers = max_subscribers(None);
+
+    quote!(
+        #[doc(hidden)]
+        #[allow(non_upper_case_globals)]
+        static __TOPIC_DeadLetters: ::make_message_bus::Topic<::make_message_bus::DroppedMessages, #capacity, #subscribers> = ::make_message_bus::Topic::new();
+
+        #[doc(hidden)]
+        fn __report_dropped(dropped: ::make_message_bus::DroppedMessages) {
+            __TOPIC_DeadLetters.publish(dropped);
+        }
+
+        /// Handle to the dead-letter topic, receiving a `DroppedMessages` event whenever a subscriber on the bus misses messages.
+        pub struct DeadLetters;
+
+        impl DeadLetters {
+            /// Subscribe to the dead-letter topic.
+            pub fn subscribe() -> ::make_message_bus::Subscriber<::make_message_bus::DroppedMessages> {
+                __TOPIC_DeadLetters.subscribe()
+            }
+
+            /// Subscribe to the dead-letter topic, with `lag_policy` deciding what happens when the subscriber lags behind.
+            pub fn subscribe_with(
+                lag_policy: ::make_message_bus::LagPolicy,
+            ) -> ::make_message_bus::Subscriber<::make_message_bus::DroppedMessages> {
+                __TOPIC_DeadLetters.subscribe().with_lag_policy(lag_policy)
+            }
+
+            /// Number of subscribers of the dead-letter topic.
+            pub fn receiver_count() -> usize {
+                __TOPIC_DeadLetters.receiver_count()
+            }
+
+            /// Checks if the dead-letter topic has any subscribers.
+            pub fn has_subscribers() -> bool {
+                __TOPIC_DeadLetters.has_subscribers()
+            }
+        }
+    )
+}
+
+/// The maximum number of subscribers of a topic, the crate's default if not set.
+fn max_subscribers(max_subscribers: Option<usize>) -> TokenStream2 {
+    match max_subscribers {
+        Some(max) => quote!(#max),
+        None => quote!({ ::make_message_bus::DEFAULT_MAX_SUBSCRIBERS }),
+    }
+}
+
 fn find_total_capacity(sub_topic: &SubTopic, capacity: &mut usize) {
     let topic_cap: usize = sub_topic
         .ast
         .topics
         .iter()
+        .filter(|topic| topic.response.is_none())
         .map(|topic| topic.capacity)
         .sum();
 
@@ -165,15 +992,22 @@ fn find_total_capacity(sub_topic: &SubTopic, capacity: &mut usize) {
     }
 }
 
-struct SubTopicTracker(Vec<Ident>);
+struct SubTopicTracker {
+    sub_topics: Vec<Ident>,
+    /// The bus has a dead-letter topic
+    dead_letters: bool,
+}
 
 impl SubTopicTracker {
-    pub fn new() -> Self {
-        Self(Vec::new())
+    pub fn new(dead_letters: bool) -> Self {
+        Self {
+            sub_topics: Vec::new(),
+            dead_letters,
+        }
     }
 
     pub fn depth(&self) -> usize {
-        self.0.len()
+        self.sub_topics.len()
     }
 
     pub fn at_root(&self) -> bool {
@@ -181,28 +1015,63 @@ impl SubTopicTracker {
     }
 
     pub fn add_subtopic(&mut self, subtopic: Ident) {
-        self.0.push(subtopic);
+        self.sub_topics.push(subtopic);
     }
 
     pub fn remove_last_subtopic(&mut self) {
-        self.0.pop();
+        self.sub_topics.pop();
+    }
+
+    /// Path of a topic in the current sub-topic, or of the sub-topic itself, e.g.
+    /// `Toplevel/SubTopic/Topic`.
+    pub fn path(&self, topic: Option<&Ident>) -> String {
+        self.sub_topics
+            .iter()
+            .chain(topic)
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>()
+            .join("/")
     }
 
-    pub fn to_parent_publishes(&self, current_topic: &Ident) -> Vec<TokenStream2> {
+    /// Builder calls setting the path of a topic in the current sub-topic, and making it report
+    /// dropped messages on the dead-letter topic if the bus has one.
+    pub fn to_lag_reporting(&self, topic: Option<&Ident>) -> TokenStream2 {
+        let path = self.path(topic);
+
+        if !self.dead_letters {
+            return quote!(.path(#path));
+        }
+
+        // The report function is in the toplevel sub-topic's module
+        let super_tokens = (1..self.depth()).map(|_| quote!(super::));
+
+        quote!(.path(#path).dead_letters(#(#super_tokens)* __report_dropped))
+    }
+
+    /// Publish to all parent topics with the `publish` method of their statics.
+    pub fn to_parent_publishes(&self, topic: &Topic, publish: &str) -> Vec<TokenStream2> {
+        let publish = Ident::new(publish, Span::call_site());
         let mut publish_tokens = Vec::new();
 
         let mut super_tokens = quote!();
-        let mut payload = quote!(payload.clone());
-        let mut last_topic = current_topic;
+        let mut payload = clone_payload(&topic.payload, quote!(&payload));
+        let mut last_topic = &topic.name;
 
-        for parent_topic in self.0.iter().rev() {
+        for parent_topic in self.sub_topics.iter().rev() {
             let parent_topic_static =
                 Ident::new(&format!("__TOPIC_{parent_topic}"), Span::call_site());
 
             payload = quote!(#super_tokens #parent_topic::#last_topic(#payload));
 
+            // Only clone and wrap the payload if someone is listening on the parent topic, the
+            // publish is counted either way
             publish_tokens.push(quote!(
-                #super_tokens #parent_topic_static.publish(#payload);
+                if #super_tokens #parent_topic_static.has_subscribers() {
+                    #super_tokens #parent_topic_static.#publish(#payload)
+                } else {
+                    #super_tokens #parent_topic_static.count_publish();
+                    0
+                }
             ));
 
             super_tokens = quote!(#super_tokens super::);
@@ -214,9 +1083,18 @@ impl SubTopicTracker {
 }
 
 pub fn generate(ast: &Ast, _anaysis: &Analysis) -> proc_macro::TokenStream {
-    let mut subtopic_tracker = SubTopicTracker::new();
+    let dead_letters = ast
+        .sub_topics
+        .iter()
+        .any(|sub_topic| sub_topic.dead_letters.is_some());
+    let mut subtopic_tracker = SubTopicTracker::new(dead_letters);
 
-    let tokens = codegen_subtopics(&ast.sub_topics, &mut subtopic_tracker);
+    let tokens = codegen_subtopics(
+        &ast.sub_topics,
+        &mut subtopic_tracker,
+        &ast.attrs,
+        &ast.derives,
+    );
 
     quote! {
         #(#tokens)*
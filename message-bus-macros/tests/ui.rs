@@ -1,7 +1,17 @@
+// The expected errors are for the default features, the other macro features are enabled by the
+// runtime crate's features and add their own errors for the invalid payloads
+#![cfg(not(any(
+    feature = "prometheus",
+    feature = "tracing",
+    feature = "serde",
+    feature = "record"
+)))]
+
 use trybuild::TestCases;
 
 #[test]
 fn ui() {
     let t = TestCases::new();
     t.compile_fail("ui/*.rs");
+    t.pass("ui/pass/*.rs");
 }
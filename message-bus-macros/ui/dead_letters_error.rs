@@ -0,0 +1,12 @@
+use message_bus_macros::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel [dead_letters = 8] => { // Ok
+        SomeData1 => u32,
+        nested::Nested [dead_letters] => { // Err
+            SomeData2 => i32,
+        },
+    },
+);
+
+fn main() {}
@@ -0,0 +1,15 @@
+use message_bus_macros::make_message_bus;
+
+make_message_bus!(
+    #![derive(Clone)] // Err
+
+    bus::Toplevel => {
+        SomeData1 => u32,
+        #[derive(PartialEq, Default)] // Err
+        nested::Nested => {
+            SomeData2 => i32,
+        },
+    },
+);
+
+fn main() {}
@@ -0,0 +1,17 @@
+use make_message_bus::make_message_bus;
+
+#[derive(Clone, Debug)]
+pub struct NotComparable;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeData1 => u32,
+        #[derive(PartialEq)]
+        nested::Nested => {
+            SomeData2 => i32,                 // Ok
+            SomeData3 => crate::NotComparable, // Err
+        },
+    },
+);
+
+fn main() {}
@@ -0,0 +1,10 @@
+use message_bus_macros::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeData1 [history = 10] => u32, // Ok
+        SomeData2 [history = 0] => i32, // Err
+    },
+);
+
+fn main() {}
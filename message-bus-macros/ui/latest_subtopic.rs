@@ -0,0 +1,11 @@
+use message_bus_macros::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        sub::Topic [latest] => { // Err
+            SomeData => u32,
+        },
+    },
+);
+
+fn main() {}
@@ -0,0 +1,20 @@
+use make_message_bus::make_message_bus;
+
+#[derive(Clone, Debug, serde::Deserialize)]
+pub struct Reading {
+    pub sensor: String,
+    pub value: f32,
+}
+
+make_message_bus!(
+    #![derive(serde::Deserialize)]
+    bus::Toplevel => {
+        SomeData1 => u32,
+        SomeData2 => crate::Reading,
+        nested::Nested => {
+            SomeData3 => (u8, String),
+        },
+    },
+);
+
+fn main() {}
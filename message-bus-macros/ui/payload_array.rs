@@ -0,0 +1,13 @@
+use make_message_bus::make_message_bus;
+
+#[derive(Debug)]
+pub struct NotClone;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeData1 => [u8; 16],             // Ok
+        SomeData2 => [crate::NotClone; 4], // Err
+    },
+);
+
+fn main() {}
@@ -0,0 +1,18 @@
+use make_message_bus::make_message_bus;
+
+#[derive(Debug)]
+pub struct NotClone;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeData1 => u8,                                          // Ok
+        sub::SubTopic => {
+            SomeData2 => crate::NotClone,                         // Err
+            SomeData3 [2, envelope, lossless] => crate::NotClone, // Err
+            SomeData4 [latest] => std::rc::Rc<u8>,                // Err
+            SomeData5 [2, queue] => crate::NotClone,              // Err
+        },
+    },
+);
+
+fn main() {}
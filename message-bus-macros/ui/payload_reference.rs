@@ -0,0 +1,12 @@
+use message_bus_macros::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeData1 => &'static str,             // Ok
+        SomeData2 => &str,                     // Err
+        SomeData3 => std::borrow::Cow<'a, str>, // Err
+        SomeData4 => fn(&u8),                  // Ok
+    },
+);
+
+fn main() {}
@@ -0,0 +1,11 @@
+use make_message_bus::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeData1 => (u8, u16),                    // Ok
+        SomeData2 => (u8, std::rc::Rc<u16>),       // Err
+        SomeRequest => (u8, u8) -> std::rc::Rc<u8>, // Err
+    },
+);
+
+fn main() {}
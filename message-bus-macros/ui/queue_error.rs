@@ -0,0 +1,10 @@
+use message_bus_macros::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeData1 [queue] => u32, // Ok
+        SomeData2 [queue, latest] => i32, // Err
+    },
+);
+
+fn main() {}
@@ -0,0 +1,10 @@
+use message_bus_macros::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeRequest1 [10] => u32 -> u32, // Ok
+        SomeRequest2 [10, latest] => u32 -> u32, // Err
+    },
+);
+
+fn main() {}
@@ -0,0 +1,10 @@
+use message_bus_macros::make_message_bus;
+
+make_message_bus!(
+    bus::Toplevel => {
+        SomeData1 [10, latest] => u32, // Ok
+        SomeData2 [10, oldest] => i32, // Err
+    },
+);
+
+fn main() {}